@@ -3,7 +3,12 @@
  * Licensed under the Server Side Public License v1 (SSPLv1).
  */
 
-use std::ffi::{c_char, c_void};
+use std::{
+    ffi::{c_char, c_void},
+    ptr::null_mut,
+};
+
+use crate::graph::matrix::delta_matrix::DeltaMatrix;
 
 pub type NodeID = i64;
 pub type EntityID = i64;
@@ -14,14 +19,67 @@ pub type AttributeID = i32;
 pub type AttributeSet = *mut c_void;
 pub type Graph = c_void;
 pub type GraphContext = c_void;
+pub type Schema = c_void;
+pub type Constraint = *mut c_void;
 
 #[repr(C)]
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum SchemaType {
     Node,
     Edge,
 }
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub union SIValueData {
+    pub longval: i64,
+    pub doubleval: f64,
+    pub ptrval: *mut c_void,
+    pub point: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct RawSIValue {
+    pub data: SIValueData,
+    pub type_: u32,
+    pub allocation: u32,
+}
+
+pub const T_MAP: u32 = 1 << 0;
+pub const T_NODE: u32 = 1 << 1;
+pub const T_EDGE: u32 = 1 << 2;
+pub const T_ARRAY: u32 = 1 << 3;
+pub const T_PATH: u32 = 1 << 4;
+pub const T_DATETIME: u32 = 1 << 5;
+pub const T_LOCALDATETIME: u32 = 1 << 6;
+pub const T_DATE: u32 = 1 << 7;
+pub const T_TIME: u32 = 1 << 8;
+pub const T_LOCALTIME: u32 = 1 << 9;
+pub const T_DURATION: u32 = 1 << 10;
+pub const T_STRING: u32 = 1 << 11;
+pub const T_BOOL: u32 = 1 << 12;
+pub const T_INT64: u32 = 1 << 13;
+pub const T_DOUBLE: u32 = 1 << 14;
+pub const T_NULL: u32 = 1 << 15;
+pub const T_POINT: u32 = 1 << 17;
+pub const T_VECTOR_F32: u32 = 1 << 18;
+pub const T_INTERN_STRING: u32 = 1 << 19;
+
+impl RawSIValue {
+    pub fn null() -> Self {
+        Self {
+            data: SIValueData { longval: 0 },
+            type_: T_NULL,
+            allocation: 0,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.type_ == T_NULL
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub enum IndexFieldType {
@@ -97,19 +155,44 @@ pub enum ConfigOptionField {
 }
 
 extern "C" {
-    fn Graph_CreateNode(
+    fn Graph_CreateNodeWithId(
         g: *mut Graph,
+        id: NodeID,
         n: *mut Node,
         labels: *mut LabelID,
         label_count: u32,
-    );
-    fn Graph_CreateEdge(
+    ) -> bool;
+    fn Graph_CreateEdgeWithId(
         g: *mut Graph,
+        id: EntityID,
         src: NodeID,
         dest: NodeID,
         r: RelationID,
         e: *mut Edge,
-    );
+    ) -> bool;
+    fn Graph_GetNode(
+        g: *const Graph,
+        id: NodeID,
+        n: *mut Node,
+    ) -> bool;
+    fn Graph_GetEdge(
+        g: *const Graph,
+        id: EntityID,
+        e: *mut Edge,
+    ) -> bool;
+    fn Graph_GetNodeLabels(
+        g: *const Graph,
+        n: *const Node,
+        labels: *mut LabelID,
+        label_count: u32,
+    ) -> u32;
+    fn Graph_NodeCount(g: *const Graph) -> u64;
+    fn Graph_EdgeCount(g: *const Graph) -> u64;
+    fn Graph_LabelTypeCount(g: *const Graph) -> i32;
+    fn Graph_GetLabelMatrix(
+        g: *const Graph,
+        label: LabelID,
+    ) -> *mut c_void;
     fn Graph_DeleteNodes(
         g: *mut Graph,
         nodes: *mut Node,
@@ -140,16 +223,47 @@ extern "C" {
         g: *mut Graph,
         relation_id: RelationID,
     );
+    #[allow(dead_code)]
+    fn Graph_AddLabel(g: *mut Graph) -> LabelID;
+    #[allow(dead_code)]
+    fn Graph_AddRelationType(g: *mut Graph) -> RelationID;
     fn GraphContext_GetGraph(gc: *mut GraphContext) -> *mut Graph;
     fn GraphContext_RemoveSchema(
         gc: *mut GraphContext,
         schema_id: i32,
         t: SchemaType,
     );
+    fn GraphContext_AddSchema(
+        gc: *mut GraphContext,
+        label: *const c_char,
+        t: SchemaType,
+    ) -> *mut Schema;
+    fn GraphContext_SchemaCount(
+        gc: *const GraphContext,
+        t: SchemaType,
+    ) -> u16;
+    fn GraphContext_AttributeCount(gc: *mut GraphContext) -> u32;
+    fn GraphContext_GetSchemaByID(
+        gc: *mut GraphContext,
+        id: i32,
+        t: SchemaType,
+    ) -> *mut Schema;
     fn GraphContext_RemoveAttribute(
         gc: *mut GraphContext,
         id: AttributeID,
     );
+    fn GraphContext_FindOrAddAttribute(
+        gc: *mut GraphContext,
+        attribute: *const c_char,
+        created: *mut bool,
+    ) -> AttributeID;
+    fn GraphContext_AddIndex(
+        gc: *mut GraphContext,
+        schema_type: SchemaType,
+        label: *const c_char,
+        field: *const c_char,
+        t: IndexFieldType,
+    ) -> i32;
     fn GraphContext_DeleteIndex(
         gc: *mut GraphContext,
         schema_type: SchemaType,
@@ -165,6 +279,16 @@ extern "C" {
         gc: *mut GraphContext,
         e: *mut Edge,
     );
+    fn GraphContext_UpdateNodeAttrInIndices(
+        gc: *mut GraphContext,
+        n: *mut Node,
+        attr_id: AttributeID,
+    );
+    fn GraphContext_UpdateEdgeAttrInIndices(
+        gc: *mut GraphContext,
+        e: *mut Edge,
+        attr_id: AttributeID,
+    );
     fn GraphContext_DeleteNodeFromIndices(
         gc: *mut GraphContext,
         n: *mut Node,
@@ -175,7 +299,42 @@ extern "C" {
         gc: *mut GraphContext,
         e: *mut Edge,
     );
+    fn Schema_GetID(s: *const Schema) -> i32;
+    fn Schema_AddConstraint(
+        s: *mut Schema,
+        c: Constraint,
+    );
+    fn Schema_RemoveConstraint(
+        s: *mut Schema,
+        c: Constraint,
+    );
+    pub fn Constraint_Free(c: *mut Constraint);
     pub fn AttributeSet_Free(set: *mut AttributeSet);
+    pub fn AttributeSet_Count(set: AttributeSet) -> u16;
+    pub fn AttributeSet_Get(
+        set: AttributeSet,
+        attr_id: AttributeID,
+    ) -> *mut RawSIValue;
+    pub fn AttributeSet_GetIdx(
+        set: AttributeSet,
+        i: u16,
+        attr_id: *mut AttributeID,
+    ) -> RawSIValue;
+    pub fn AttributeSet_Update(
+        set: *mut AttributeSet,
+        attr_id: AttributeID,
+        value: RawSIValue,
+    ) -> bool;
+    pub fn SIValue_Free(v: RawSIValue);
+    pub fn SI_CloneValue(v: RawSIValue) -> RawSIValue;
+    pub fn SI_DuplicateStringVal(s: *const c_char) -> RawSIValue;
+    pub fn SIValue_Persist(v: *mut RawSIValue);
+    pub fn SIValue_Compare(
+        a: RawSIValue,
+        b: RawSIValue,
+        disjoint_or_null: *mut i32,
+    ) -> i32;
+    pub fn SIValue_memoryUsage(v: RawSIValue) -> usize;
     pub fn Config_Option_get(
         field: ConfigOptionField,
         ...
@@ -192,26 +351,85 @@ pub struct GraphAPI {
 }
 
 impl GraphAPI {
-    pub fn create_node(
+    pub fn get_node(
+        &self,
+        id: NodeID,
+    ) -> Option<Node> {
+        let mut n = Node {
+            attributes: null_mut(),
+            id: -1,
+        };
+        unsafe { Graph_GetNode(self.graph, id, &mut n) }.then_some(n)
+    }
+    pub fn get_edge(
+        &self,
+        id: EntityID,
+    ) -> Option<Edge> {
+        let mut e = Edge {
+            attributes: null_mut(),
+            id: -1,
+            relationship: null_mut(),
+            relation_id: -1,
+            src_id: -1,
+            dest_id: -1,
+        };
+        unsafe { Graph_GetEdge(self.graph, id, &mut e) }.then_some(e)
+    }
+    pub fn contains_node(
+        &self,
+        id: NodeID,
+    ) -> bool {
+        self.get_node(id).is_some()
+    }
+    pub fn contains_edge(
+        &self,
+        id: EntityID,
+    ) -> bool {
+        self.get_edge(id).is_some()
+    }
+    pub fn node_labels(
+        &self,
+        n: &Node,
+    ) -> Vec<LabelID> {
+        let mut labels = vec![0; self.label_count().max(0) as usize];
+        let count =
+            unsafe { Graph_GetNodeLabels(self.graph, n, labels.as_mut_ptr(), labels.len() as u32) };
+        labels.truncate(count as usize);
+        labels
+    }
+    pub fn node_count(&self) -> u64 {
+        unsafe { Graph_NodeCount(self.graph) }
+    }
+    pub fn edge_count(&self) -> u64 {
+        unsafe { Graph_EdgeCount(self.graph) }
+    }
+    pub fn label_count(&self) -> i32 {
+        unsafe { Graph_LabelTypeCount(self.graph) }
+    }
+    pub fn label_matrix(
+        &self,
+        label: LabelID,
+    ) -> &DeltaMatrix {
+        unsafe { &*(Graph_GetLabelMatrix(self.graph, label) as *const DeltaMatrix) }
+    }
+    pub fn create_node_with_id(
         &mut self,
+        id: NodeID,
         n: *mut Node,
         labels: *mut LabelID,
         label_count: u32,
-    ) {
-        unsafe {
-            Graph_CreateNode(self.graph, n, labels, label_count);
-        }
+    ) -> bool {
+        unsafe { Graph_CreateNodeWithId(self.graph, id, n, labels, label_count) }
     }
-    pub fn create_edge(
+    pub fn create_edge_with_id(
         &mut self,
+        id: EntityID,
         src: NodeID,
         dest: NodeID,
         r: RelationID,
         e: *mut Edge,
-    ) {
-        unsafe {
-            Graph_CreateEdge(self.graph, src, dest, r, e);
-        }
+    ) -> bool {
+        unsafe { Graph_CreateEdgeWithId(self.graph, id, src, dest, r, e) }
     }
     pub fn delete_nodes(
         &mut self,
@@ -267,6 +485,15 @@ impl GraphAPI {
             Graph_RemoveRelation(self.graph, relation_id);
         }
     }
+    // used by tests to set up schemas
+    #[allow(dead_code)]
+    pub fn add_label(&mut self) -> LabelID {
+        unsafe { Graph_AddLabel(self.graph) }
+    }
+    #[allow(dead_code)]
+    pub fn add_relation_type(&mut self) -> RelationID {
+        unsafe { Graph_AddRelationType(self.graph) }
+    }
 }
 
 pub struct GraphContextAPI {
@@ -291,6 +518,22 @@ impl GraphContextAPI {
             GraphContext_RemoveSchema(self.context, schema_id, t);
         }
     }
+    pub fn add_schema(
+        &self,
+        label: *const c_char,
+        t: SchemaType,
+    ) -> i32 {
+        unsafe { Schema_GetID(GraphContext_AddSchema(self.context, label, t)) }
+    }
+    pub fn schema_count(
+        &self,
+        t: SchemaType,
+    ) -> i32 {
+        unsafe { GraphContext_SchemaCount(self.context, t) as i32 }
+    }
+    pub fn attribute_count(&self) -> AttributeID {
+        unsafe { GraphContext_AttributeCount(self.context) as AttributeID }
+    }
     pub fn remove_attribute(
         &self,
         id: AttributeID,
@@ -299,6 +542,21 @@ impl GraphContextAPI {
             GraphContext_RemoveAttribute(self.context, id);
         }
     }
+    pub fn find_or_add_attribute(
+        &self,
+        attribute: *const c_char,
+    ) -> AttributeID {
+        unsafe { GraphContext_FindOrAddAttribute(self.context, attribute, null_mut()) }
+    }
+    pub fn add_index(
+        &self,
+        schema_type: SchemaType,
+        label: *const c_char,
+        field: *const c_char,
+        t: IndexFieldType,
+    ) -> i32 {
+        unsafe { GraphContext_AddIndex(self.context, schema_type, label, field, t) }
+    }
     pub fn delete_index(
         &self,
         schema_type: SchemaType,
@@ -309,6 +567,27 @@ impl GraphContextAPI {
         unsafe { GraphContext_DeleteIndex(self.context, schema_type, label, field, t) }
     }
 
+    pub fn add_constraint(
+        &self,
+        schema_id: i32,
+        t: SchemaType,
+        c: Constraint,
+    ) {
+        unsafe {
+            Schema_AddConstraint(GraphContext_GetSchemaByID(self.context, schema_id, t), c);
+        }
+    }
+    pub fn remove_constraint(
+        &self,
+        schema_id: i32,
+        t: SchemaType,
+        c: Constraint,
+    ) {
+        unsafe {
+            Schema_RemoveConstraint(GraphContext_GetSchemaByID(self.context, schema_id, t), c);
+        }
+    }
+
     pub fn add_node_to_indices(
         &self,
         n: *mut Node,
@@ -325,6 +604,24 @@ impl GraphContextAPI {
             GraphContext_AddEdgeToIndices(self.context, e);
         }
     }
+    pub fn update_node_attr_in_indices(
+        &self,
+        n: *mut Node,
+        attr_id: AttributeID,
+    ) {
+        unsafe {
+            GraphContext_UpdateNodeAttrInIndices(self.context, n, attr_id);
+        }
+    }
+    pub fn update_edge_attr_in_indices(
+        &self,
+        e: *mut Edge,
+        attr_id: AttributeID,
+    ) {
+        unsafe {
+            GraphContext_UpdateEdgeAttrInIndices(self.context, e, attr_id);
+        }
+    }
     pub fn delete_node_from_indices(
         &self,
         n: *mut Node,
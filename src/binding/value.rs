@@ -0,0 +1,672 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{
+    cmp::Ordering,
+    ffi::{c_void, CStr, CString},
+    hash::{Hash, Hasher},
+    slice::from_raw_parts,
+};
+
+use super::graph::*;
+
+extern "C" {
+    fn SIArray_New(initial_capacity: u32) -> RawSIValue;
+    fn SIArray_Append(
+        siarray: *mut RawSIValue,
+        value: RawSIValue,
+    );
+    fn SIArray_Length(siarray: RawSIValue) -> u32;
+    fn SIArray_Get(
+        siarray: RawSIValue,
+        index: u32,
+    ) -> RawSIValue;
+    fn Map_New(capacity: u32) -> RawSIValue;
+    fn Map_Add(
+        map: *mut RawSIValue,
+        key: RawSIValue,
+        value: RawSIValue,
+    );
+    fn Map_KeyCount(map: RawSIValue) -> u32;
+    fn Map_GetIdx(
+        map: RawSIValue,
+        idx: u32,
+        key: *mut RawSIValue,
+        value: *mut RawSIValue,
+    );
+    fn SIVectorf32_New(dim: u32) -> RawSIValue;
+    fn SIVector_Dim(vector: RawSIValue) -> u32;
+    fn SIVector_Elements(vector: RawSIValue) -> *mut c_void;
+    fn SIPath_Length(path: RawSIValue) -> usize;
+    fn SIPath_NodeCount(path: RawSIValue) -> usize;
+    fn SIPath_GetNode(
+        path: RawSIValue,
+        i: usize,
+    ) -> RawSIValue;
+    fn SIPath_GetRelationship(
+        path: RawSIValue,
+        i: usize,
+    ) -> RawSIValue;
+}
+
+/// A property or query value of the engine.
+/// Graph entities are referenced by id.
+#[derive(Clone, Debug)]
+pub enum SIValue {
+    Null,
+    Bool(bool),
+    Int64(i64),
+    Double(f64),
+    String(String),
+    Array(Vec<SIValue>),
+    Map(Vec<(String, SIValue)>),
+    Point {
+        latitude: f32,
+        longitude: f32,
+    },
+    VectorF32(Vec<f32>),
+    DateTime(i64),
+    LocalDateTime(i64),
+    Date(i64),
+    Time(i64),
+    LocalTime(i64),
+    Duration(i64),
+    Node(NodeID),
+    Edge(EntityID),
+    Path {
+        nodes: Vec<NodeID>,
+        edges: Vec<EntityID>,
+    },
+}
+
+fn raw(
+    type_: u32,
+    data: SIValueData,
+) -> RawSIValue {
+    RawSIValue {
+        data,
+        type_,
+        allocation: 0,
+    }
+}
+
+impl SIValue {
+    /// Convert a value of the engine, the raw value is borrowed.
+    ///
+    /// # Safety
+    ///
+    /// v must be a valid value of the engine.
+    pub unsafe fn from_raw(v: &RawSIValue) -> Self {
+        match v.type_ {
+            T_BOOL => Self::Bool(v.data.longval != 0),
+            T_INT64 => Self::Int64(v.data.longval),
+            T_DOUBLE => Self::Double(v.data.doubleval),
+            T_STRING | T_INTERN_STRING => Self::String(
+                CStr::from_ptr(v.data.ptrval as *const _)
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+            T_ARRAY => Self::Array(
+                (0..SIArray_Length(*v))
+                    .map(|i| Self::from_raw(&SIArray_Get(*v, i)))
+                    .collect(),
+            ),
+            T_MAP => Self::Map(
+                (0..Map_KeyCount(*v))
+                    .map(|i| {
+                        let mut key = RawSIValue::null();
+                        let mut value = RawSIValue::null();
+                        Map_GetIdx(*v, i, &mut key, &mut value);
+                        let key = match Self::from_raw(&key) {
+                            Self::String(key) => key,
+                            _ => String::new(),
+                        };
+                        (key, Self::from_raw(&value))
+                    })
+                    .collect(),
+            ),
+            T_POINT => Self::Point {
+                latitude: v.data.point[0],
+                longitude: v.data.point[1],
+            },
+            T_VECTOR_F32 => {
+                let dim = SIVector_Dim(*v) as usize;
+                let elements = SIVector_Elements(*v) as *const f32;
+                Self::VectorF32(if dim == 0 {
+                    vec![]
+                } else {
+                    from_raw_parts(elements, dim).to_vec()
+                })
+            }
+            T_DATETIME => Self::DateTime(v.data.longval),
+            T_LOCALDATETIME => Self::LocalDateTime(v.data.longval),
+            T_DATE => Self::Date(v.data.longval),
+            T_TIME => Self::Time(v.data.longval),
+            T_LOCALTIME => Self::LocalTime(v.data.longval),
+            T_DURATION => Self::Duration(v.data.longval),
+            T_NODE => Self::Node((*(v.data.ptrval as *const Node)).id),
+            T_EDGE => Self::Edge((*(v.data.ptrval as *const Edge)).id),
+            T_PATH => Self::Path {
+                nodes: (0..SIPath_NodeCount(*v))
+                    .map(|i| (*(SIPath_GetNode(*v, i).data.ptrval as *const Node)).id)
+                    .collect(),
+                edges: (0..SIPath_Length(*v))
+                    .map(|i| (*(SIPath_GetRelationship(*v, i).data.ptrval as *const Edge)).id)
+                    .collect(),
+            },
+            _ => Self::Null,
+        }
+    }
+
+    /// Convert to a value of the engine owned by the caller and freed with SIValue_Free.
+    /// Graph entities can not be created without the graph and return None.
+    pub fn to_raw(&self) -> Option<RawSIValue> {
+        Some(match self {
+            Self::Null => RawSIValue::null(),
+            Self::Bool(b) => raw(T_BOOL, SIValueData { longval: *b as i64 }),
+            Self::Int64(i) => raw(T_INT64, SIValueData { longval: *i }),
+            Self::Double(d) => raw(T_DOUBLE, SIValueData { doubleval: *d }),
+            Self::String(s) => {
+                let s = CString::new(s.as_str()).ok()?;
+                unsafe { SI_DuplicateStringVal(s.as_ptr()) }
+            }
+            Self::Array(values) => unsafe {
+                let mut arr = SIArray_New(values.len() as u32);
+                for v in values {
+                    let Some(v) = v.to_raw() else {
+                        SIValue_Free(arr);
+                        return None;
+                    };
+                    SIArray_Append(&mut arr, v);
+                    SIValue_Free(v);
+                }
+                arr
+            },
+            Self::Map(entries) => unsafe {
+                let mut map = Map_New(entries.len() as u32);
+                for (k, v) in entries {
+                    let (Some(k), Some(v)) = (Self::String(k.clone()).to_raw(), v.to_raw()) else {
+                        SIValue_Free(map);
+                        return None;
+                    };
+                    Map_Add(&mut map, k, v);
+                    SIValue_Free(k);
+                    SIValue_Free(v);
+                }
+                map
+            },
+            Self::Point {
+                latitude,
+                longitude,
+            } => raw(
+                T_POINT,
+                SIValueData {
+                    point: [*latitude, *longitude],
+                },
+            ),
+            Self::VectorF32(elements) => unsafe {
+                let v = SIVectorf32_New(elements.len() as u32);
+                if !elements.is_empty() {
+                    (SIVector_Elements(v) as *mut f32)
+                        .copy_from_nonoverlapping(elements.as_ptr(), elements.len());
+                }
+                v
+            },
+            Self::DateTime(t) => raw(T_DATETIME, SIValueData { longval: *t }),
+            Self::LocalDateTime(t) => raw(T_LOCALDATETIME, SIValueData { longval: *t }),
+            Self::Date(t) => raw(T_DATE, SIValueData { longval: *t }),
+            Self::Time(t) => raw(T_TIME, SIValueData { longval: *t }),
+            Self::LocalTime(t) => raw(T_LOCALTIME, SIValueData { longval: *t }),
+            Self::Duration(t) => raw(T_DURATION, SIValueData { longval: *t }),
+            Self::Node(_) | Self::Edge(_) | Self::Path { .. } => return None,
+        })
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    // position of the type in the Cypher orderability
+    // numbers share a rank and null sorts last
+    fn rank(&self) -> u32 {
+        match self {
+            Self::Map(_) => T_MAP,
+            Self::Node(_) => T_NODE,
+            Self::Edge(_) => T_EDGE,
+            Self::Array(_) => T_ARRAY,
+            Self::Path { .. } => T_PATH,
+            Self::DateTime(_) => T_DATETIME,
+            Self::LocalDateTime(_) => T_LOCALDATETIME,
+            Self::Date(_) => T_DATE,
+            Self::Time(_) => T_TIME,
+            Self::LocalTime(_) => T_LOCALTIME,
+            Self::Duration(_) => T_DURATION,
+            Self::String(_) => T_STRING,
+            Self::Bool(_) => T_BOOL,
+            Self::Int64(_) | Self::Double(_) => T_INT64,
+            Self::Point { .. } => T_POINT,
+            Self::VectorF32(_) => T_VECTOR_F32,
+            Self::Null => u32::MAX,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Int64(i) => Some(*i as f64),
+            Self::Double(d) => Some(*d),
+            _ => None,
+        }
+    }
+
+    /// Cypher `=`, None when the result is null.
+    pub fn cypher_eq(
+        &self,
+        other: &Self,
+    ) -> Option<bool> {
+        if self.is_null() || other.is_null() {
+            return None;
+        }
+        if self.rank() != other.rank() {
+            return Some(false);
+        }
+        match (self, other) {
+            (Self::Int64(a), Self::Int64(b)) => Some(a == b),
+            (Self::Int64(_) | Self::Double(_), _) => {
+                Some(self.as_f64().unwrap() == other.as_f64().unwrap())
+            }
+            (Self::Array(a), Self::Array(b)) => {
+                if a.len() != b.len() {
+                    return Some(false);
+                }
+                all_eq(a.iter().zip(b.iter()))
+            }
+            (Self::Map(a), Self::Map(b)) => {
+                if a.len() != b.len() {
+                    return Some(false);
+                }
+                let mut pairs = Vec::with_capacity(a.len());
+                for (k, v) in a {
+                    let Some((_, other)) = b.iter().find(|(ok, _)| ok == k) else {
+                        return Some(false);
+                    };
+                    pairs.push((v, other));
+                }
+                all_eq(pairs.into_iter())
+            }
+            _ => Some(self == other),
+        }
+    }
+}
+
+// false wins over null which wins over true
+fn all_eq<'a>(pairs: impl Iterator<Item = (&'a SIValue, &'a SIValue)>) -> Option<bool> {
+    let mut res = Some(true);
+    for (a, b) in pairs {
+        match a.cypher_eq(b) {
+            Some(false) => return Some(false),
+            None => res = None,
+            Some(true) => {}
+        }
+    }
+    res
+}
+
+fn cmp_numbers(
+    a: &SIValue,
+    b: &SIValue,
+) -> Ordering {
+    if let (SIValue::Int64(a), SIValue::Int64(b)) = (a, b) {
+        return a.cmp(b);
+    }
+    let (a, b) = (a.as_f64().unwrap(), b.as_f64().unwrap());
+    // NaN is greater than any other number
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => a.partial_cmp(&b).unwrap(),
+    }
+}
+
+fn sorted_entries(entries: &[(String, SIValue)]) -> Vec<&(String, SIValue)> {
+    let mut entries = entries.iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+/// Cypher orderability, total across types as used by ORDER BY.
+impl Ord for SIValue {
+    fn cmp(
+        &self,
+        other: &Self,
+    ) -> Ordering {
+        let rank = self.rank().cmp(&other.rank());
+        if rank != Ordering::Equal {
+            return rank;
+        }
+        match (self, other) {
+            (Self::Int64(_) | Self::Double(_), _) => cmp_numbers(self, other),
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::String(a), Self::String(b)) => a.cmp(b),
+            (Self::Array(a), Self::Array(b)) => a.cmp(b),
+            (Self::Map(a), Self::Map(b)) => sorted_entries(a).cmp(&sorted_entries(b)),
+            (
+                Self::Point {
+                    latitude: a_lat,
+                    longitude: a_lon,
+                },
+                Self::Point {
+                    latitude: b_lat,
+                    longitude: b_lon,
+                },
+            ) => a_lat.total_cmp(b_lat).then_with(|| a_lon.total_cmp(b_lon)),
+            (Self::VectorF32(a), Self::VectorF32(b)) => a
+                .iter()
+                .zip(b.iter())
+                .map(|(a, b)| a.total_cmp(b))
+                .find(|o| o.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (Self::DateTime(a), Self::DateTime(b))
+            | (Self::LocalDateTime(a), Self::LocalDateTime(b))
+            | (Self::Date(a), Self::Date(b))
+            | (Self::Time(a), Self::Time(b))
+            | (Self::LocalTime(a), Self::LocalTime(b))
+            | (Self::Duration(a), Self::Duration(b))
+            | (Self::Node(a), Self::Node(b))
+            | (Self::Edge(a), Self::Edge(b)) => a.cmp(b),
+            (
+                Self::Path {
+                    nodes: a_nodes,
+                    edges: a_edges,
+                },
+                Self::Path {
+                    nodes: b_nodes,
+                    edges: b_edges,
+                },
+            ) => a_nodes.cmp(b_nodes).then_with(|| a_edges.cmp(b_edges)),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+impl PartialOrd for SIValue {
+    fn partial_cmp(
+        &self,
+        other: &Self,
+    ) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Equivalence as used by DISTINCT and grouping, null is equivalent to null.
+impl PartialEq for SIValue {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SIValue {}
+
+/// Consistent with equivalence, equal integers and doubles hash the same.
+impl Hash for SIValue {
+    fn hash<H: Hasher>(
+        &self,
+        state: &mut H,
+    ) {
+        self.rank().hash(state);
+        match self {
+            Self::Null => {}
+            Self::Int64(_) | Self::Double(_) => {
+                let d = self.as_f64().unwrap();
+                let bits = if d.is_nan() {
+                    f64::NAN.to_bits()
+                } else if d == 0.0 {
+                    0
+                } else {
+                    d.to_bits()
+                };
+                bits.hash(state);
+            }
+            Self::Bool(b) => b.hash(state),
+            Self::String(s) => s.hash(state),
+            Self::Array(a) => a.hash(state),
+            Self::Map(m) => sorted_entries(m).hash(state),
+            Self::Point {
+                latitude,
+                longitude,
+            } => {
+                latitude.to_bits().hash(state);
+                longitude.to_bits().hash(state);
+            }
+            Self::VectorF32(v) => {
+                for f in v {
+                    f.to_bits().hash(state);
+                }
+            }
+            Self::DateTime(t)
+            | Self::LocalDateTime(t)
+            | Self::Date(t)
+            | Self::Time(t)
+            | Self::LocalTime(t)
+            | Self::Duration(t)
+            | Self::Node(t)
+            | Self::Edge(t) => t.hash(state),
+            Self::Path { nodes, edges } => {
+                nodes.hash(state);
+                edges.hash(state);
+            }
+        }
+    }
+}
+
+impl From<bool> for SIValue {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<i32> for SIValue {
+    fn from(i: i32) -> Self {
+        Self::Int64(i as i64)
+    }
+}
+
+impl From<i64> for SIValue {
+    fn from(i: i64) -> Self {
+        Self::Int64(i)
+    }
+}
+
+impl From<f64> for SIValue {
+    fn from(d: f64) -> Self {
+        Self::Double(d)
+    }
+}
+
+impl From<&str> for SIValue {
+    fn from(s: &str) -> Self {
+        Self::String(s.to_owned())
+    }
+}
+
+impl From<String> for SIValue {
+    fn from(s: String) -> Self {
+        Self::String(s)
+    }
+}
+
+impl From<Vec<f32>> for SIValue {
+    fn from(v: Vec<f32>) -> Self {
+        Self::VectorF32(v)
+    }
+}
+
+impl<T: Into<SIValue>> From<Vec<T>> for SIValue {
+    fn from(v: Vec<T>) -> Self {
+        Self::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<SIValue>> From<Option<T>> for SIValue {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Self::Null, Into::into)
+    }
+}
+
+impl TryFrom<SIValue> for bool {
+    type Error = SIValue;
+
+    fn try_from(v: SIValue) -> Result<Self, Self::Error> {
+        match v {
+            SIValue::Bool(b) => Ok(b),
+            v => Err(v),
+        }
+    }
+}
+
+impl TryFrom<SIValue> for i64 {
+    type Error = SIValue;
+
+    fn try_from(v: SIValue) -> Result<Self, Self::Error> {
+        match v {
+            SIValue::Int64(i) => Ok(i),
+            v => Err(v),
+        }
+    }
+}
+
+impl TryFrom<SIValue> for f64 {
+    type Error = SIValue;
+
+    fn try_from(v: SIValue) -> Result<Self, Self::Error> {
+        match v {
+            SIValue::Int64(i) => Ok(i as f64),
+            SIValue::Double(d) => Ok(d),
+            v => Err(v),
+        }
+    }
+}
+
+impl TryFrom<SIValue> for String {
+    type Error = SIValue;
+
+    fn try_from(v: SIValue) -> Result<Self, Self::Error> {
+        match v {
+            SIValue::String(s) => Ok(s),
+            v => Err(v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::hash_map::DefaultHasher, collections::HashSet};
+
+    use super::*;
+
+    fn hash(v: &SIValue) -> u64 {
+        let mut h = DefaultHasher::new();
+        v.hash(&mut h);
+        h.finish()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let values = vec![
+            SIValue::Null,
+            true.into(),
+            42i64.into(),
+            (-1.5).into(),
+            "hello".into(),
+            vec![SIValue::from(1), "a".into(), SIValue::Null].into(),
+            SIValue::Map(vec![
+                ("a".to_owned(), 1.into()),
+                ("b".to_owned(), vec![2.5].into()),
+            ]),
+            SIValue::Point {
+                latitude: 32.07,
+                longitude: 34.78,
+            },
+            vec![0.5f32, 1.5, -2.0].into(),
+            SIValue::DateTime(1_700_000_000),
+            SIValue::Date(1_699_920_000),
+            SIValue::Duration(3600),
+        ];
+
+        for v in values {
+            let raw = v.to_raw().unwrap();
+            let back = unsafe { SIValue::from_raw(&raw) };
+            unsafe { SIValue_Free(raw) };
+            assert_eq!(back, v);
+        }
+
+        assert!(SIValue::Node(1).to_raw().is_none());
+    }
+
+    #[test]
+    fn test_cypher_eq() {
+        let null = SIValue::Null;
+        assert_eq!(null.cypher_eq(&null), None);
+        assert_eq!(SIValue::from(1).cypher_eq(&1.0.into()), Some(true));
+        assert_eq!(SIValue::from(1).cypher_eq(&"1".into()), Some(false));
+
+        let nan = SIValue::from(f64::NAN);
+        assert_eq!(nan.cypher_eq(&nan), Some(false));
+        assert_eq!(nan, nan);
+
+        let a = SIValue::from(vec![SIValue::from(1), SIValue::Null]);
+        let b = SIValue::from(vec![SIValue::from(1), SIValue::from(2)]);
+        let c = SIValue::from(vec![SIValue::from(2), SIValue::Null]);
+        assert_eq!(a.cypher_eq(&b), None);
+        assert_eq!(a.cypher_eq(&c), Some(false));
+    }
+
+    #[test]
+    fn test_order_and_hash() {
+        let mut values = vec![
+            SIValue::Null,
+            f64::NAN.into(),
+            2.into(),
+            1.5.into(),
+            true.into(),
+            "b".into(),
+            "a".into(),
+            vec![SIValue::from(1)].into(),
+            SIValue::Map(vec![]),
+        ];
+        values.sort();
+        assert_eq!(
+            values,
+            vec![
+                SIValue::Map(vec![]),
+                vec![SIValue::from(1)].into(),
+                "a".into(),
+                "b".into(),
+                true.into(),
+                1.5.into(),
+                2.into(),
+                f64::NAN.into(),
+                SIValue::Null,
+            ]
+        );
+
+        assert_eq!(SIValue::from(1), SIValue::from(1.0));
+        assert_eq!(hash(&1.into()), hash(&1.0.into()));
+        assert_eq!(hash(&0.0.into()), hash(&(-0.0).into()));
+
+        let a = SIValue::Map(vec![("x".to_owned(), 1.into()), ("y".to_owned(), 2.into())]);
+        let b = SIValue::Map(vec![
+            ("y".to_owned(), 2.into()),
+            ("x".to_owned(), 1.0.into()),
+        ]);
+        assert_eq!(a, b);
+
+        let set: HashSet<SIValue> = [a, b, 1.into(), 1.0.into()].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+}
@@ -0,0 +1,201 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use crate::binding::graph::*;
+
+pub type SubscriptionId = u64;
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ChangeType {
+    NodeCreated,
+    NodeDeleted,
+    NodeUpdated,
+    EdgeCreated,
+    EdgeDeleted,
+    EdgeUpdated,
+    LabelsAdded,
+    LabelsRemoved,
+}
+
+/// A single change made by a committed transaction.
+/// src_id, dest_id and relation_id are set only for edge changes
+/// labels is set only for label changes.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ChangeEvent {
+    pub change_type: ChangeType,
+    pub id: EntityID,
+    pub src_id: NodeID,
+    pub dest_id: NodeID,
+    pub relation_id: RelationID,
+    pub labels: Vec<LabelID>,
+}
+
+impl ChangeEvent {
+    pub fn node(
+        change_type: ChangeType,
+        id: NodeID,
+    ) -> Self {
+        Self {
+            change_type,
+            id,
+            src_id: -1,
+            dest_id: -1,
+            relation_id: -1,
+            labels: vec![],
+        }
+    }
+
+    pub fn edge(
+        change_type: ChangeType,
+        edge: &Edge,
+    ) -> Self {
+        Self {
+            change_type,
+            id: edge.id,
+            src_id: edge.src_id,
+            dest_id: edge.dest_id,
+            relation_id: edge.relation_id,
+            labels: vec![],
+        }
+    }
+
+    pub fn labels(
+        change_type: ChangeType,
+        id: NodeID,
+        labels: &[LabelID],
+    ) -> Self {
+        Self {
+            change_type,
+            id,
+            src_id: -1,
+            dest_id: -1,
+            relation_id: -1,
+            labels: labels.to_vec(),
+        }
+    }
+}
+
+type Subscriber = Arc<dyn Fn(*mut GraphContext, &[ChangeEvent]) + Send + Sync>;
+
+static SUBSCRIBERS: Mutex<Vec<(SubscriptionId, Subscriber)>> = Mutex::new(Vec::new());
+static SUBSCRIBERS_COUNT: AtomicUsize = AtomicUsize::new(0);
+static NEXT_SUBSCRIPTION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Register f to be called with the changes of every committed transaction.
+pub fn subscribe<F>(f: F) -> SubscriptionId
+where
+    F: Fn(*mut GraphContext, &[ChangeEvent]) + Send + Sync + 'static,
+{
+    let id = NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed);
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    subscribers.push((id, Arc::new(f)));
+    SUBSCRIBERS_COUNT.store(subscribers.len(), Ordering::Release);
+    id
+}
+
+/// Remove a subscriber, returns false if it was not registered.
+pub fn unsubscribe(id: SubscriptionId) -> bool {
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    let len = subscribers.len();
+    subscribers.retain(|(sid, _)| *sid != id);
+    SUBSCRIBERS_COUNT.store(subscribers.len(), Ordering::Release);
+    subscribers.len() != len
+}
+
+/// Returns true if anyone listens so producers can skip building the batch.
+pub fn has_subscribers() -> bool {
+    SUBSCRIBERS_COUNT.load(Ordering::Acquire) > 0
+}
+
+/// Deliver a batch of changes made to the graph to all subscribers.
+/// Subscribers are called outside the lock so they may (un)subscribe.
+pub fn publish(
+    gc: *mut GraphContext,
+    events: &[ChangeEvent],
+) {
+    if events.is_empty() {
+        return;
+    }
+
+    let subscribers = SUBSCRIBERS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(_, s)| s.clone())
+        .collect::<Vec<_>>();
+    for s in subscribers {
+        s(gc, events);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::binding::graph::GraphContext;
+
+    use super::{has_subscribers, publish, subscribe, unsubscribe, ChangeEvent, ChangeType};
+
+    // the subscribers are global so each test publishes to its own fake graph
+    fn fake_gc(id: usize) -> *mut GraphContext {
+        id as *mut GraphContext
+    }
+
+    #[test]
+    fn test_publish() {
+        let gc = fake_gc(1);
+        let received = Arc::new(Mutex::new(vec![]));
+        let r = received.clone();
+        let id = subscribe(move |g, events| {
+            if g == fake_gc(1) {
+                r.lock().unwrap().extend_from_slice(events);
+            }
+        });
+        assert!(has_subscribers());
+
+        let events = vec![
+            ChangeEvent::node(ChangeType::NodeCreated, 0),
+            ChangeEvent::labels(ChangeType::LabelsAdded, 0, &[1, 2]),
+        ];
+        publish(gc, &events);
+        // empty batches are not delivered
+        publish(gc, &[]);
+        assert_eq!(*received.lock().unwrap(), events);
+
+        assert!(unsubscribe(id));
+        assert!(!unsubscribe(id));
+
+        publish(gc, &events);
+        assert_eq!(received.lock().unwrap().len(), events.len());
+    }
+
+    #[test]
+    fn test_unsubscribe_in_callback() {
+        let gc = fake_gc(2);
+        let calls = Arc::new(Mutex::new(0));
+        let id = Arc::new(Mutex::new(None));
+        let (c, i) = (calls.clone(), id.clone());
+        *id.lock().unwrap() = Some(subscribe(move |g, _| {
+            if g == fake_gc(2) {
+                *c.lock().unwrap() += 1;
+                // the subscribers lock is not held while calling back
+                if let Some(id) = i.lock().unwrap().take() {
+                    assert!(unsubscribe(id));
+                }
+            }
+        }));
+
+        let events = vec![ChangeEvent::node(ChangeType::NodeDeleted, 3)];
+        publish(gc, &events);
+        publish(gc, &events);
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+}
@@ -0,0 +1,75 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{ffi::c_void, ptr::null};
+
+use crate::{binding::graph::*, guard::ffi_guard};
+
+use super::feed::*;
+
+/// C view of a [`ChangeEvent`], labels is valid only during the callback.
+#[repr(C)]
+struct CChangeEvent {
+    change_type: ChangeType,
+    id: EntityID,
+    src_id: NodeID,
+    dest_id: NodeID,
+    relation_id: RelationID,
+    labels: *const LabelID,
+    labels_count: usize,
+}
+
+type ChangesCallback = extern "C" fn(*mut GraphContext, *const CChangeEvent, usize, *mut c_void);
+
+/// Context of a C subscriber, the subscriber is responsible for its thread safety.
+struct CSubscriber {
+    cb: ChangesCallback,
+    ctx: *mut c_void,
+}
+
+unsafe impl Send for CSubscriber {}
+unsafe impl Sync for CSubscriber {}
+
+impl CSubscriber {
+    fn notify(
+        &self,
+        gc: *mut GraphContext,
+        events: &[ChangeEvent],
+    ) {
+        let events = events
+            .iter()
+            .map(|e| CChangeEvent {
+                change_type: e.change_type,
+                id: e.id,
+                src_id: e.src_id,
+                dest_id: e.dest_id,
+                relation_id: e.relation_id,
+                labels: if e.labels.is_empty() {
+                    null()
+                } else {
+                    e.labels.as_ptr()
+                },
+                labels_count: e.labels.len(),
+            })
+            .collect::<Vec<_>>();
+        (self.cb)(gc, events.as_ptr(), events.len(), self.ctx);
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn CDC_Subscribe(
+    cb: ChangesCallback,
+    ctx: *mut c_void,
+) -> SubscriptionId {
+    ffi_guard!(SubscriptionId::MAX, {
+        let s = CSubscriber { cb, ctx };
+        subscribe(move |gc, events| s.notify(gc, events))
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn CDC_Unsubscribe(id: SubscriptionId) -> bool {
+    ffi_guard!(false, { unsubscribe(id) })
+}
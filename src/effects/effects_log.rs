@@ -0,0 +1,527 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{
+    cell::Cell,
+    ffi::{CStr, CString},
+};
+
+use crate::{binding::graph::*, guard::Poison};
+
+/// Version of the binary format written by [`EffectsLog`].
+const EFFECTS_VERSION: u8 = 1;
+
+/// Tag written before each effect.
+#[repr(u8)]
+pub enum EffectType {
+    CreateNode = 1,
+    CreateEdge = 2,
+    DeleteNode = 3,
+    DeleteEdge = 4,
+    SetLabels = 5,
+    RemoveLabels = 6,
+    UpdateNode = 7,
+    UpdateEdge = 8,
+    AddSchema = 9,
+    AddAttribute = 10,
+}
+
+/// A single forward operation decoded from an effects buffer.
+/// Attribute values are kept in the engine serialized form.
+#[derive(Debug, PartialEq)]
+pub enum Effect {
+    CreateNode {
+        id: NodeID,
+        labels: Vec<LabelID>,
+        attributes: Vec<u8>,
+    },
+    CreateEdge {
+        id: EntityID,
+        src_id: NodeID,
+        dest_id: NodeID,
+        relation_id: RelationID,
+        attributes: Vec<u8>,
+    },
+    DeleteNode {
+        id: NodeID,
+    },
+    DeleteEdge {
+        id: EntityID,
+        src_id: NodeID,
+        dest_id: NodeID,
+        relation_id: RelationID,
+    },
+    SetLabels {
+        id: NodeID,
+        labels: Vec<LabelID>,
+    },
+    RemoveLabels {
+        id: NodeID,
+        labels: Vec<LabelID>,
+    },
+    UpdateNode {
+        id: NodeID,
+        attribute_id: AttributeID,
+        value: Vec<u8>,
+    },
+    UpdateEdge {
+        id: EntityID,
+        src_id: NodeID,
+        dest_id: NodeID,
+        relation_id: RelationID,
+        attribute_id: AttributeID,
+        value: Vec<u8>,
+    },
+    AddSchema {
+        name: CString,
+        schema_type: SchemaType,
+    },
+    AddAttribute {
+        name: CString,
+    },
+}
+
+/// Records the forward operations of a write query into a compact binary buffer
+/// that replicas can replay instead of executing the query.
+/// Integers are LEB128 encoded, signed integers are zigzag encoded first.
+pub struct EffectsLog {
+    buf: Vec<u8>,
+    count: u64,
+    poisoned: Cell<bool>,
+}
+
+impl Poison for EffectsLog {
+    fn poison(&self) {
+        self.poisoned.set(true);
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.poisoned.get()
+    }
+}
+
+impl EffectsLog {
+    pub fn new() -> Self {
+        Self {
+            buf: vec![EFFECTS_VERSION],
+            count: 0,
+            poisoned: Cell::new(false),
+        }
+    }
+
+    /// Returns the encoded effects.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns the number of recorded effects.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    fn write_u64(
+        &mut self,
+        mut v: u64,
+    ) {
+        loop {
+            let b = (v & 0x7f) as u8;
+            v >>= 7;
+            if v == 0 {
+                self.buf.push(b);
+                return;
+            }
+            self.buf.push(b | 0x80);
+        }
+    }
+
+    fn write_i64(
+        &mut self,
+        v: i64,
+    ) {
+        self.write_u64(((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    fn write_bytes(
+        &mut self,
+        bytes: &[u8],
+    ) {
+        self.write_u64(bytes.len() as u64);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn write_labels(
+        &mut self,
+        labels: &[LabelID],
+    ) {
+        self.write_u64(labels.len() as u64);
+        for label in labels {
+            self.write_i64(*label as i64);
+        }
+    }
+
+    fn write_type(
+        &mut self,
+        t: EffectType,
+    ) {
+        self.buf.push(t as u8);
+        self.count += 1;
+    }
+
+    pub fn create_node(
+        &mut self,
+        id: NodeID,
+        labels: &[LabelID],
+        attributes: &[u8],
+    ) {
+        self.write_type(EffectType::CreateNode);
+        self.write_i64(id);
+        self.write_labels(labels);
+        self.write_bytes(attributes);
+    }
+
+    pub fn create_edge(
+        &mut self,
+        id: EntityID,
+        src_id: NodeID,
+        dest_id: NodeID,
+        relation_id: RelationID,
+        attributes: &[u8],
+    ) {
+        self.write_type(EffectType::CreateEdge);
+        self.write_i64(id);
+        self.write_i64(src_id);
+        self.write_i64(dest_id);
+        self.write_i64(relation_id as i64);
+        self.write_bytes(attributes);
+    }
+
+    pub fn delete_node(
+        &mut self,
+        id: NodeID,
+    ) {
+        self.write_type(EffectType::DeleteNode);
+        self.write_i64(id);
+    }
+
+    pub fn delete_edge(
+        &mut self,
+        id: EntityID,
+        src_id: NodeID,
+        dest_id: NodeID,
+        relation_id: RelationID,
+    ) {
+        self.write_type(EffectType::DeleteEdge);
+        self.write_i64(id);
+        self.write_i64(src_id);
+        self.write_i64(dest_id);
+        self.write_i64(relation_id as i64);
+    }
+
+    pub fn set_labels(
+        &mut self,
+        id: NodeID,
+        labels: &[LabelID],
+    ) {
+        self.write_type(EffectType::SetLabels);
+        self.write_i64(id);
+        self.write_labels(labels);
+    }
+
+    pub fn remove_labels(
+        &mut self,
+        id: NodeID,
+        labels: &[LabelID],
+    ) {
+        self.write_type(EffectType::RemoveLabels);
+        self.write_i64(id);
+        self.write_labels(labels);
+    }
+
+    pub fn update_node(
+        &mut self,
+        id: NodeID,
+        attribute_id: AttributeID,
+        value: &[u8],
+    ) {
+        self.write_type(EffectType::UpdateNode);
+        self.write_i64(id);
+        self.write_i64(attribute_id as i64);
+        self.write_bytes(value);
+    }
+
+    pub fn update_edge(
+        &mut self,
+        id: EntityID,
+        src_id: NodeID,
+        dest_id: NodeID,
+        relation_id: RelationID,
+        attribute_id: AttributeID,
+        value: &[u8],
+    ) {
+        self.write_type(EffectType::UpdateEdge);
+        self.write_i64(id);
+        self.write_i64(src_id);
+        self.write_i64(dest_id);
+        self.write_i64(relation_id as i64);
+        self.write_i64(attribute_id as i64);
+        self.write_bytes(value);
+    }
+
+    pub fn add_schema(
+        &mut self,
+        name: &CStr,
+        schema_type: SchemaType,
+    ) {
+        self.write_type(EffectType::AddSchema);
+        self.write_bytes(name.to_bytes());
+        self.buf.push(schema_type as u8);
+    }
+
+    pub fn add_attribute(
+        &mut self,
+        name: &CStr,
+    ) {
+        self.write_type(EffectType::AddAttribute);
+        self.write_bytes(name.to_bytes());
+    }
+}
+
+/// Reads the values written by [`EffectsLog`].
+struct EffectsReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> EffectsReader<'a> {
+    fn read_u8(&mut self) -> Result<u8, ()> {
+        let b = *self.buf.get(self.pos).ok_or(())?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn read_u64(&mut self) -> Result<u64, ()> {
+        let mut v = 0u64;
+        let mut shift = 0;
+        loop {
+            if shift >= u64::BITS {
+                return Err(());
+            }
+            let b = self.read_u8()?;
+            v |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+            shift += 7;
+        }
+    }
+
+    fn read_i64(&mut self) -> Result<i64, ()> {
+        let v = self.read_u64()?;
+        Ok(((v >> 1) as i64) ^ -((v & 1) as i64))
+    }
+
+    fn read_i32(&mut self) -> Result<i32, ()> {
+        i32::try_from(self.read_i64()?).map_err(|_| ())
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], ()> {
+        let len = self.read_u64()? as usize;
+        let end = self.pos.checked_add(len).ok_or(())?;
+        let bytes = self.buf.get(self.pos..end).ok_or(())?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_name(&mut self) -> Result<CString, ()> {
+        CString::new(self.read_bytes()?).map_err(|_| ())
+    }
+
+    fn read_labels(&mut self) -> Result<Vec<LabelID>, ()> {
+        let len = self.read_u64()? as usize;
+        let mut labels = Vec::with_capacity(len.min(self.buf.len()));
+        for _ in 0..len {
+            labels.push(self.read_i32()?);
+        }
+        Ok(labels)
+    }
+
+    fn read_effect(&mut self) -> Result<Effect, ()> {
+        let t = self.read_u8()?;
+        let effect = match t {
+            t if t == EffectType::CreateNode as u8 => Effect::CreateNode {
+                id: self.read_i64()?,
+                labels: self.read_labels()?,
+                attributes: self.read_bytes()?.to_vec(),
+            },
+            t if t == EffectType::CreateEdge as u8 => Effect::CreateEdge {
+                id: self.read_i64()?,
+                src_id: self.read_i64()?,
+                dest_id: self.read_i64()?,
+                relation_id: self.read_i32()?,
+                attributes: self.read_bytes()?.to_vec(),
+            },
+            t if t == EffectType::DeleteNode as u8 => Effect::DeleteNode {
+                id: self.read_i64()?,
+            },
+            t if t == EffectType::DeleteEdge as u8 => Effect::DeleteEdge {
+                id: self.read_i64()?,
+                src_id: self.read_i64()?,
+                dest_id: self.read_i64()?,
+                relation_id: self.read_i32()?,
+            },
+            t if t == EffectType::SetLabels as u8 => Effect::SetLabels {
+                id: self.read_i64()?,
+                labels: self.read_labels()?,
+            },
+            t if t == EffectType::RemoveLabels as u8 => Effect::RemoveLabels {
+                id: self.read_i64()?,
+                labels: self.read_labels()?,
+            },
+            t if t == EffectType::UpdateNode as u8 => Effect::UpdateNode {
+                id: self.read_i64()?,
+                attribute_id: self.read_i32()?,
+                value: self.read_bytes()?.to_vec(),
+            },
+            t if t == EffectType::UpdateEdge as u8 => Effect::UpdateEdge {
+                id: self.read_i64()?,
+                src_id: self.read_i64()?,
+                dest_id: self.read_i64()?,
+                relation_id: self.read_i32()?,
+                attribute_id: self.read_i32()?,
+                value: self.read_bytes()?.to_vec(),
+            },
+            t if t == EffectType::AddSchema as u8 => Effect::AddSchema {
+                name: self.read_name()?,
+                schema_type: match self.read_u8()? {
+                    t if t == SchemaType::Node as u8 => SchemaType::Node,
+                    t if t == SchemaType::Edge as u8 => SchemaType::Edge,
+                    _ => return Err(()),
+                },
+            },
+            t if t == EffectType::AddAttribute as u8 => Effect::AddAttribute {
+                name: self.read_name()?,
+            },
+            _ => return Err(()),
+        };
+        Ok(effect)
+    }
+}
+
+/// Decode a buffer produced by [`EffectsLog`].
+///
+/// # Errors
+///
+/// This function will return an error if the buffer is truncated, corrupted
+/// or was written by a different version.
+pub fn decode(buf: &[u8]) -> Result<Vec<Effect>, ()> {
+    let mut reader = EffectsReader { buf, pos: 0 };
+    if reader.read_u8()? != EFFECTS_VERSION {
+        return Err(());
+    }
+
+    let mut effects = Vec::new();
+    while reader.pos < buf.len() {
+        effects.push(reader.read_effect()?);
+    }
+    Ok(effects)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use crate::binding::graph::SchemaType;
+
+    use super::{decode, Effect, EffectsLog};
+
+    #[test]
+    fn test_round_trip() {
+        let mut log = EffectsLog::new();
+        let person = CString::new("Person").unwrap();
+        let name = CString::new("name").unwrap();
+
+        log.add_schema(&person, SchemaType::Node);
+        log.add_attribute(&name);
+        log.create_node(0, &[0], &[1, 2, 3]);
+        log.create_node(1 << 40, &[0, 1], &[]);
+        log.create_edge(7, 0, 1 << 40, 3, &[4]);
+        log.set_labels(0, &[2]);
+        log.remove_labels(0, &[0]);
+        log.update_node(0, 0, &[5, 6]);
+        log.update_edge(7, 0, 1 << 40, 3, 1, &[]);
+        log.delete_edge(7, 0, 1 << 40, 3);
+        log.delete_node(-1);
+
+        assert_eq!(log.count(), 11);
+
+        let effects = decode(log.buffer()).unwrap();
+        assert_eq!(
+            effects,
+            vec![
+                Effect::AddSchema {
+                    name: person,
+                    schema_type: SchemaType::Node,
+                },
+                Effect::AddAttribute { name },
+                Effect::CreateNode {
+                    id: 0,
+                    labels: vec![0],
+                    attributes: vec![1, 2, 3],
+                },
+                Effect::CreateNode {
+                    id: 1 << 40,
+                    labels: vec![0, 1],
+                    attributes: vec![],
+                },
+                Effect::CreateEdge {
+                    id: 7,
+                    src_id: 0,
+                    dest_id: 1 << 40,
+                    relation_id: 3,
+                    attributes: vec![4],
+                },
+                Effect::SetLabels {
+                    id: 0,
+                    labels: vec![2],
+                },
+                Effect::RemoveLabels {
+                    id: 0,
+                    labels: vec![0],
+                },
+                Effect::UpdateNode {
+                    id: 0,
+                    attribute_id: 0,
+                    value: vec![5, 6],
+                },
+                Effect::UpdateEdge {
+                    id: 7,
+                    src_id: 0,
+                    dest_id: 1 << 40,
+                    relation_id: 3,
+                    attribute_id: 1,
+                    value: vec![],
+                },
+                Effect::DeleteEdge {
+                    id: 7,
+                    src_id: 0,
+                    dest_id: 1 << 40,
+                    relation_id: 3,
+                },
+                Effect::DeleteNode { id: -1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_corrupted() {
+        let mut log = EffectsLog::new();
+        log.create_node(1, &[0], &[1, 2, 3]);
+
+        let buf = log.buffer();
+        assert!(decode(&buf[..buf.len() - 1]).is_err());
+        assert!(decode(&[0]).is_err());
+        assert_eq!(decode(&buf[..1]), Ok(vec![]));
+    }
+}
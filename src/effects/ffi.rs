@@ -0,0 +1,344 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{
+    ffi::{c_char, c_void, CStr},
+    ptr::{null, null_mut},
+    slice::from_raw_parts,
+};
+
+use crate::{binding::graph::*, guard::ffi_guard};
+
+use super::effects_log::*;
+
+type _EffectsLog = *mut EffectsLog;
+
+/// C view of a decoded [`Effect`], pointers are valid only during the callback.
+#[repr(C)]
+struct EffectView {
+    effect_type: u8,
+    id: EntityID,
+    src_id: NodeID,
+    dest_id: NodeID,
+    relation_id: RelationID,
+    attribute_id: AttributeID,
+    schema_type: SchemaType,
+    labels: *const LabelID,
+    labels_count: usize,
+    // attributes, updated value or schema/attribute name
+    data: *const u8,
+    data_len: usize,
+}
+
+// C passes NULL for empty arrays which from_raw_parts does not accept
+unsafe fn slice<'a, T>(
+    data: *const T,
+    len: usize,
+) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        from_raw_parts(data, len)
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_New() -> _EffectsLog {
+    ffi_guard!(null_mut(), { Box::into_raw(Box::new(EffectsLog::new())) })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_CreateNode(
+    log: _EffectsLog,
+    node: *const Node,
+    labels: *const LabelID,
+    labels_count: usize,
+    attributes: *const u8,
+    attributes_len: usize,
+) {
+    ffi_guard!(log, (), {
+        (*log).create_node(
+            (*node).id,
+            slice(labels, labels_count),
+            slice(attributes, attributes_len),
+        );
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_CreateEdge(
+    log: _EffectsLog,
+    edge: *const Edge,
+    attributes: *const u8,
+    attributes_len: usize,
+) {
+    ffi_guard!(log, (), {
+        let e = &*edge;
+        (*log).create_edge(
+            e.id,
+            e.src_id,
+            e.dest_id,
+            e.relation_id,
+            slice(attributes, attributes_len),
+        );
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_DeleteNode(
+    log: _EffectsLog,
+    node: *const Node,
+) {
+    ffi_guard!(log, (), {
+        (*log).delete_node((*node).id);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_DeleteEdge(
+    log: _EffectsLog,
+    edge: *const Edge,
+) {
+    ffi_guard!(log, (), {
+        let e = &*edge;
+        (*log).delete_edge(e.id, e.src_id, e.dest_id, e.relation_id);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_AddLabels(
+    log: _EffectsLog,
+    node: *const Node,
+    label_ids: *const LabelID,
+    labels_count: usize,
+) {
+    ffi_guard!(log, (), {
+        (*log).set_labels((*node).id, slice(label_ids, labels_count));
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_RemoveLabels(
+    log: _EffectsLog,
+    node: *const Node,
+    label_ids: *const LabelID,
+    labels_count: usize,
+) {
+    ffi_guard!(log, (), {
+        (*log).remove_labels((*node).id, slice(label_ids, labels_count));
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_UpdateNode(
+    log: _EffectsLog,
+    node: *const Node,
+    attribute_id: AttributeID,
+    value: *const u8,
+    value_len: usize,
+) {
+    ffi_guard!(log, (), {
+        (*log).update_node((*node).id, attribute_id, slice(value, value_len));
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_UpdateEdge(
+    log: _EffectsLog,
+    edge: *const Edge,
+    attribute_id: AttributeID,
+    value: *const u8,
+    value_len: usize,
+) {
+    ffi_guard!(log, (), {
+        let e = &*edge;
+        (*log).update_edge(
+            e.id,
+            e.src_id,
+            e.dest_id,
+            e.relation_id,
+            attribute_id,
+            slice(value, value_len),
+        );
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_AddSchema(
+    log: _EffectsLog,
+    name: *const c_char,
+    t: SchemaType,
+) {
+    ffi_guard!(log, (), {
+        (*log).add_schema(CStr::from_ptr(name), t);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_AddAttribute(
+    log: _EffectsLog,
+    name: *const c_char,
+) {
+    ffi_guard!(log, (), {
+        (*log).add_attribute(CStr::from_ptr(name));
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_Count(log: _EffectsLog) -> u64 {
+    ffi_guard!(log, 0, { (&*log).count() })
+}
+
+/// Returns NULL and a zero len if the log was poisoned by a panic.
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_Buffer(
+    log: _EffectsLog,
+    len: *mut usize,
+) -> *const u8 {
+    *len = 0;
+    ffi_guard!(log, null(), {
+        let buf = (&*log).buffer();
+        *len = buf.len();
+        buf.as_ptr()
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn EffectsLog_Free(log: _EffectsLog) {
+    ffi_guard!((), {
+        drop(Box::from_raw(log));
+    })
+}
+
+/// Decode an effects buffer and call cb for each effect in order.
+/// Returns false if the buffer is corrupted, in that case cb is not called.
+#[no_mangle]
+unsafe extern "C" fn Effects_Replay(
+    buf: *const u8,
+    len: usize,
+    cb: extern "C" fn(*const EffectView, *mut c_void),
+    ctx: *mut c_void,
+) -> bool {
+    ffi_guard!(false, {
+        let Ok(effects) = decode(slice(buf, len)) else {
+            return false;
+        };
+
+        for effect in effects.iter() {
+            let mut view = EffectView {
+                effect_type: 0,
+                id: -1,
+                src_id: -1,
+                dest_id: -1,
+                relation_id: -1,
+                attribute_id: -1,
+                schema_type: SchemaType::Node,
+                labels: null(),
+                labels_count: 0,
+                data: null(),
+                data_len: 0,
+            };
+            match effect {
+                Effect::CreateNode {
+                    id,
+                    labels,
+                    attributes,
+                } => {
+                    view.effect_type = EffectType::CreateNode as u8;
+                    view.id = *id;
+                    view.labels = labels.as_ptr();
+                    view.labels_count = labels.len();
+                    view.data = attributes.as_ptr();
+                    view.data_len = attributes.len();
+                }
+                Effect::CreateEdge {
+                    id,
+                    src_id,
+                    dest_id,
+                    relation_id,
+                    attributes,
+                } => {
+                    view.effect_type = EffectType::CreateEdge as u8;
+                    view.id = *id;
+                    view.src_id = *src_id;
+                    view.dest_id = *dest_id;
+                    view.relation_id = *relation_id;
+                    view.data = attributes.as_ptr();
+                    view.data_len = attributes.len();
+                }
+                Effect::DeleteNode { id } => {
+                    view.effect_type = EffectType::DeleteNode as u8;
+                    view.id = *id;
+                }
+                Effect::DeleteEdge {
+                    id,
+                    src_id,
+                    dest_id,
+                    relation_id,
+                } => {
+                    view.effect_type = EffectType::DeleteEdge as u8;
+                    view.id = *id;
+                    view.src_id = *src_id;
+                    view.dest_id = *dest_id;
+                    view.relation_id = *relation_id;
+                }
+                Effect::SetLabels { id, labels } => {
+                    view.effect_type = EffectType::SetLabels as u8;
+                    view.id = *id;
+                    view.labels = labels.as_ptr();
+                    view.labels_count = labels.len();
+                }
+                Effect::RemoveLabels { id, labels } => {
+                    view.effect_type = EffectType::RemoveLabels as u8;
+                    view.id = *id;
+                    view.labels = labels.as_ptr();
+                    view.labels_count = labels.len();
+                }
+                Effect::UpdateNode {
+                    id,
+                    attribute_id,
+                    value,
+                } => {
+                    view.effect_type = EffectType::UpdateNode as u8;
+                    view.id = *id;
+                    view.attribute_id = *attribute_id;
+                    view.data = value.as_ptr();
+                    view.data_len = value.len();
+                }
+                Effect::UpdateEdge {
+                    id,
+                    src_id,
+                    dest_id,
+                    relation_id,
+                    attribute_id,
+                    value,
+                } => {
+                    view.effect_type = EffectType::UpdateEdge as u8;
+                    view.id = *id;
+                    view.src_id = *src_id;
+                    view.dest_id = *dest_id;
+                    view.relation_id = *relation_id;
+                    view.attribute_id = *attribute_id;
+                    view.data = value.as_ptr();
+                    view.data_len = value.len();
+                }
+                Effect::AddSchema { name, schema_type } => {
+                    view.effect_type = EffectType::AddSchema as u8;
+                    view.schema_type = *schema_type;
+                    view.data = name.as_ptr().cast();
+                    view.data_len = name.as_bytes().len();
+                }
+                Effect::AddAttribute { name } => {
+                    view.effect_type = EffectType::AddAttribute as u8;
+                    view.data = name.as_ptr().cast();
+                    view.data_len = name.as_bytes().len();
+                }
+            }
+            cb(&view, ctx);
+        }
+
+        true
+    })
+}
@@ -0,0 +1,353 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{
+    ffi::{CStr, CString},
+    mem::size_of,
+    ptr::null_mut,
+};
+
+use crate::binding::graph::{
+    AttributeID, RawSIValue, SIValueData, SIValue_Compare, SIValue_Free, SIValue_Persist,
+    SIValue_memoryUsage, SI_CloneValue, SI_DuplicateStringVal, T_BOOL, T_DOUBLE, T_INT64, T_NULL,
+    T_POINT, T_STRING,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum SerializeError {
+    // the value type has no binary encoding
+    UnsupportedType(u32),
+    Corrupted,
+}
+
+// the engine counts attributes with a u16
+pub const MAX_ATTRIBUTES: usize = u16::MAX as usize;
+
+/// Attributes of a graph entity sorted by attribute id.
+/// The set owns its values and frees them on drop.
+#[derive(Default)]
+pub struct AttributeSet {
+    attrs: Vec<(AttributeID, RawSIValue)>,
+}
+
+impl Drop for AttributeSet {
+    fn drop(&mut self) {
+        for (_, v) in self.attrs.drain(..) {
+            unsafe { SIValue_Free(v) };
+        }
+    }
+}
+
+impl Clone for AttributeSet {
+    fn clone(&self) -> Self {
+        Self {
+            attrs: self
+                .attrs
+                .iter()
+                .map(|(id, v)| (*id, unsafe { SI_CloneValue(*v) }))
+                .collect(),
+        }
+    }
+}
+
+impl AttributeSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.attrs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attrs.is_empty()
+    }
+
+    fn find(
+        &self,
+        id: AttributeID,
+    ) -> Result<usize, usize> {
+        self.attrs
+            .binary_search_by_key(&id, |(attr_id, _)| *attr_id)
+    }
+
+    pub fn get(
+        &self,
+        id: AttributeID,
+    ) -> Option<&RawSIValue> {
+        self.find(id).ok().map(|i| &self.attrs[i].1)
+    }
+
+    pub fn get_idx(
+        &self,
+        i: usize,
+    ) -> Option<(AttributeID, &RawSIValue)> {
+        self.attrs.get(i).map(|(id, v)| (*id, v))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (AttributeID, &RawSIValue)> {
+        self.attrs.iter().map(|(id, v)| (*id, v))
+    }
+
+    // insert at i, a full set frees value and returns false
+    fn insert(
+        &mut self,
+        i: usize,
+        id: AttributeID,
+        value: RawSIValue,
+    ) -> bool {
+        if self.attrs.len() == MAX_ATTRIBUTES {
+            log::error!("attribute set is full, attribute {id} dropped");
+            unsafe { SIValue_Free(value) };
+            return false;
+        }
+        self.attrs.insert(i, (id, value));
+        true
+    }
+
+    /// Add a clone of value replacing the current value.
+    /// Returns false if the set is full.
+    pub fn add(
+        &mut self,
+        id: AttributeID,
+        value: RawSIValue,
+    ) -> bool {
+        self.add_no_clone(id, unsafe { SI_CloneValue(value) })
+    }
+
+    /// Add value taking its ownership replacing the current value.
+    /// Returns false if the set is full.
+    pub fn add_no_clone(
+        &mut self,
+        id: AttributeID,
+        value: RawSIValue,
+    ) -> bool {
+        match self.find(id) {
+            Ok(i) => {
+                let current = std::mem::replace(&mut self.attrs[i].1, value);
+                unsafe { SIValue_Free(current) };
+                true
+            }
+            Err(i) => self.insert(i, id, value),
+        }
+    }
+
+    /// Set the attribute to a clone of value, a null value removes it.
+    /// Returns true if the set changed.
+    pub fn update(
+        &mut self,
+        id: AttributeID,
+        value: RawSIValue,
+    ) -> bool {
+        if value.is_null() {
+            return self.remove(id);
+        }
+
+        match self.find(id) {
+            Ok(i) => {
+                let current = &mut self.attrs[i].1;
+                if unsafe { SIValue_Compare(*current, value, null_mut()) } == 0 {
+                    return false;
+                }
+                unsafe {
+                    SIValue_Free(*current);
+                    *current = SI_CloneValue(value);
+                }
+            }
+            Err(i) => return self.insert(i, id, unsafe { SI_CloneValue(value) }),
+        }
+        true
+    }
+
+    /// Returns true if the attribute was removed.
+    pub fn remove(
+        &mut self,
+        id: AttributeID,
+    ) -> bool {
+        let Ok(i) = self.find(id) else {
+            return false;
+        };
+        let (_, v) = self.attrs.remove(i);
+        unsafe { SIValue_Free(v) };
+        true
+    }
+
+    /// Make every value own its memory.
+    pub fn persist(&mut self) {
+        for (_, v) in self.attrs.iter_mut() {
+            unsafe { SIValue_Persist(v) };
+        }
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.attrs.capacity() * size_of::<(AttributeID, RawSIValue)>()
+            + self
+                .attrs
+                .iter()
+                .map(|(_, v)| unsafe { SIValue_memoryUsage(*v) })
+                .sum::<usize>()
+    }
+
+    /// Append the set to buf, fails on values without a binary encoding.
+    pub fn serialize(
+        &self,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), SerializeError> {
+        let count = u16::try_from(self.attrs.len()).map_err(|_| SerializeError::Corrupted)?;
+        buf.extend_from_slice(&count.to_le_bytes());
+        for (id, v) in self.attrs.iter() {
+            buf.extend_from_slice(&id.to_le_bytes());
+            buf.extend_from_slice(&v.type_.to_le_bytes());
+            match v.type_ {
+                T_NULL => {}
+                T_BOOL | T_INT64 | T_DOUBLE | T_POINT => {
+                    buf.extend_from_slice(&unsafe { v.data.longval }.to_le_bytes());
+                }
+                T_STRING => {
+                    let s = unsafe { CStr::from_ptr(v.data.ptrval as *const _) }.to_bytes();
+                    buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                    buf.extend_from_slice(s);
+                }
+                t => return Err(SerializeError::UnsupportedType(t)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a set written by [`AttributeSet::serialize`] from the start of buf.
+    /// Returns the set and the number of bytes consumed.
+    pub fn deserialize(buf: &[u8]) -> Result<(Self, usize), SerializeError> {
+        let mut pos = 0;
+        let mut take = |n: usize| -> Result<&[u8], SerializeError> {
+            let bytes = buf.get(pos..pos + n).ok_or(SerializeError::Corrupted)?;
+            pos += n;
+            Ok(bytes)
+        };
+
+        let count = u16::from_le_bytes(take(2)?.try_into().unwrap());
+        let mut set = Self {
+            attrs: Vec::with_capacity(count as usize),
+        };
+        for _ in 0..count {
+            let id = AttributeID::from_le_bytes(take(4)?.try_into().unwrap());
+            let type_ = u32::from_le_bytes(take(4)?.try_into().unwrap());
+            let value = match type_ {
+                T_NULL => RawSIValue::null(),
+                T_BOOL | T_INT64 | T_DOUBLE | T_POINT => RawSIValue {
+                    data: SIValueData {
+                        longval: i64::from_le_bytes(take(8)?.try_into().unwrap()),
+                    },
+                    type_,
+                    allocation: 0,
+                },
+                T_STRING => {
+                    let len = u32::from_le_bytes(take(4)?.try_into().unwrap());
+                    let s =
+                        CString::new(take(len as usize)?).map_err(|_| SerializeError::Corrupted)?;
+                    unsafe { SI_DuplicateStringVal(s.as_ptr()) }
+                }
+                t => return Err(SerializeError::UnsupportedType(t)),
+            };
+            // ids are written sorted, anything else is corrupted
+            if set.attrs.last().is_some_and(|(last, _)| *last >= id) {
+                unsafe { SIValue_Free(value) };
+                return Err(SerializeError::Corrupted);
+            }
+            set.attrs.push((id, value));
+        }
+        Ok((set, pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(v: i64) -> RawSIValue {
+        RawSIValue {
+            data: SIValueData { longval: v },
+            type_: T_INT64,
+            allocation: 0,
+        }
+    }
+
+    fn get_int(
+        set: &AttributeSet,
+        id: AttributeID,
+    ) -> Option<i64> {
+        set.get(id).map(|v| unsafe { v.data.longval })
+    }
+
+    #[test]
+    fn test_update() {
+        let mut set = AttributeSet::new();
+        set.add(3, int(3));
+        set.add(1, int(1));
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.get_idx(0).map(|(id, _)| id), Some(1));
+
+        assert!(set.update(2, int(2)));
+        assert!(!set.update(2, int(2)));
+        assert!(set.update(3, int(4)));
+        assert_eq!(get_int(&set, 3), Some(4));
+
+        assert!(set.update(1, RawSIValue::null()));
+        assert!(!set.remove(1));
+        assert_eq!(get_int(&set, 1), None);
+        assert_eq!(set.iter().map(|(id, _)| id).collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut set = AttributeSet::new();
+        for i in 0..10 {
+            set.add(i * 2, int(i as i64 * 100));
+        }
+        // null values are kept by add_no_clone
+        set.add_no_clone(21, RawSIValue::null());
+        set.add_no_clone(23, unsafe { SI_DuplicateStringVal(c"falkor".as_ptr()) });
+
+        let mut buf = vec![];
+        set.serialize(&mut buf).unwrap();
+        let (clone, read) = AttributeSet::deserialize(&buf).unwrap();
+        assert_eq!(read, buf.len());
+        assert_eq!(clone.len(), set.len());
+        for (id, _) in set.iter().filter(|(id, _)| *id < 20) {
+            assert_eq!(get_int(&clone, id), get_int(&set, id));
+        }
+        assert!(clone.get(21).is_some_and(|v| v.is_null()));
+        let s = clone.get(23).unwrap();
+        assert_eq!(s.type_, T_STRING);
+        assert_eq!(
+            unsafe { CStr::from_ptr(s.data.ptrval as *const _) },
+            c"falkor"
+        );
+
+        assert_eq!(
+            AttributeSet::deserialize(&buf[..buf.len() - 1]).err(),
+            Some(SerializeError::Corrupted)
+        );
+    }
+
+    #[test]
+    fn test_add() {
+        let mut set = AttributeSet::new();
+        assert!(set.add_no_clone(1, int(1)));
+        // an existing value is replaced
+        assert!(set.add_no_clone(1, int(2)));
+        assert_eq!(set.len(), 1);
+        assert_eq!(get_int(&set, 1), Some(2));
+
+        for id in 2..=MAX_ATTRIBUTES as AttributeID {
+            assert!(set.add(id, int(id as i64)));
+        }
+        assert_eq!(set.len(), MAX_ATTRIBUTES);
+        assert!(!set.add(0, int(0)));
+        assert!(!set.update(0, int(0)));
+        assert_eq!(get_int(&set, 0), None);
+        assert_eq!(set.len(), MAX_ATTRIBUTES);
+    }
+}
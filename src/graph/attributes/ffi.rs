@@ -0,0 +1,150 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{ptr::null_mut, slice::from_raw_parts};
+
+use crate::{
+    binding::graph::{AttributeID, RawSIValue, SIValue_Free},
+    guard::ffi_guard,
+};
+
+use super::attribute_set::AttributeSet;
+
+// a null set is an empty set
+type _AttributeSet = *mut AttributeSet;
+
+unsafe fn set_mut(set: *mut _AttributeSet) -> &'static mut AttributeSet {
+    if (*set).is_null() {
+        *set = Box::into_raw(Box::new(AttributeSet::new()));
+    }
+    &mut **set
+}
+
+#[no_mangle]
+unsafe extern "C" fn AttributeSet_Count(set: _AttributeSet) -> u16 {
+    // the set never holds more than u16::MAX attributes
+    ffi_guard!(0, { set.as_ref().map_or(0, |s| s.len() as u16) })
+}
+
+#[no_mangle]
+unsafe extern "C" fn AttributeSet_Get(
+    set: _AttributeSet,
+    attr_id: AttributeID,
+) -> *mut RawSIValue {
+    ffi_guard!(null_mut(), {
+        set.as_ref()
+            .and_then(|s| s.get(attr_id))
+            .map_or(null_mut(), |v| v as *const _ as *mut _)
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn AttributeSet_GetIdx(
+    set: _AttributeSet,
+    i: u16,
+    attr_id: *mut AttributeID,
+) -> RawSIValue {
+    ffi_guard!(RawSIValue::null(), {
+        let Some((id, v)) = set.as_ref().and_then(|s| s.get_idx(i as usize)) else {
+            return RawSIValue::null();
+        };
+        if !attr_id.is_null() {
+            *attr_id = id;
+        }
+        *v
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn AttributeSet_Add(
+    set: *mut _AttributeSet,
+    attr_id: AttributeID,
+    value: RawSIValue,
+) {
+    ffi_guard!((), {
+        set_mut(set).add(attr_id, value);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn AttributeSet_AddNoClone(
+    set: *mut _AttributeSet,
+    ids: *const AttributeID,
+    values: *const RawSIValue,
+    n: u16,
+    allow_null: bool,
+) {
+    ffi_guard!((), {
+        if n == 0 {
+            return;
+        }
+        let s = set_mut(set);
+        let ids = from_raw_parts(ids, n as usize);
+        let values = from_raw_parts(values, n as usize);
+        for (id, v) in ids.iter().zip(values) {
+            if !allow_null && v.is_null() {
+                SIValue_Free(*v);
+                continue;
+            }
+            s.add_no_clone(*id, *v);
+        }
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn AttributeSet_Update(
+    set: *mut _AttributeSet,
+    attr_id: AttributeID,
+    value: RawSIValue,
+) -> bool {
+    ffi_guard!(false, {
+        if (*set).is_null() && value.is_null() {
+            return false;
+        }
+        set_mut(set).update(attr_id, value)
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn AttributeSet_Remove(
+    set: *mut _AttributeSet,
+    attr_id: AttributeID,
+) -> bool {
+    ffi_guard!(false, {
+        (*set).as_mut().is_some_and(|s| s.remove(attr_id))
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn AttributeSet_Clone(set: _AttributeSet) -> _AttributeSet {
+    ffi_guard!(null_mut(), {
+        set.as_ref()
+            .map_or(null_mut(), |s| Box::into_raw(Box::new(s.clone())))
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn AttributeSet_PersistValues(set: _AttributeSet) {
+    ffi_guard!((), {
+        if let Some(s) = set.as_mut() {
+            s.persist();
+        }
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn AttributeSet_memoryUsage(set: _AttributeSet) -> usize {
+    ffi_guard!(0, { set.as_ref().map_or(0, |s| s.memory_usage()) })
+}
+
+#[no_mangle]
+unsafe extern "C" fn AttributeSet_Free(set: *mut _AttributeSet) {
+    ffi_guard!((), {
+        if !(*set).is_null() {
+            drop(Box::from_raw(*set));
+            *set = null_mut();
+        }
+    })
+}
@@ -0,0 +1,8 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+pub mod attribute_set;
+#[cfg(feature = "rust_attribute_set")]
+mod ffi;
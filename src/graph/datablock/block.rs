@@ -0,0 +1,485 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{
+    marker::PhantomData,
+    mem::{align_of, size_of},
+    ptr,
+};
+
+// set in the header of a deleted item
+const DELETED_MARKER: u64 = 1 << 63;
+
+/// Untyped storage of fixed size items allocated in blocks.
+/// Blocks never move so item addresses are stable.
+/// Every item is preceded by a header word marking it deleted.
+pub(super) struct RawDataBlock {
+    // item size rounded up to words plus the header word
+    stride: usize,
+    block_cap: u64,
+    // owned blocks of block_cap * stride words from Box::into_raw
+    // raw so items can be written while the block is shared
+    blocks: Vec<*mut u64>,
+    // number of live items
+    item_count: u64,
+    // items below high were allocated at least once
+    high: u64,
+    deleted: Vec<u64>,
+}
+
+// the blocks are owned like a Box<[u64]>
+unsafe impl Send for RawDataBlock {}
+unsafe impl Sync for RawDataBlock {}
+
+impl Drop for RawDataBlock {
+    fn drop(&mut self) {
+        let len = self.block_cap as usize * self.stride;
+        for block in self.blocks.drain(..) {
+            drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(block, len)) });
+        }
+    }
+}
+
+impl RawDataBlock {
+    pub(super) fn new(
+        block_cap: u64,
+        item_cap: u64,
+        item_size: usize,
+    ) -> Self {
+        let mut db = Self {
+            stride: 1 + item_size.div_ceil(size_of::<u64>()),
+            block_cap: block_cap.max(1),
+            blocks: Vec::new(),
+            item_count: 0,
+            high: 0,
+            deleted: Vec::new(),
+        };
+        db.reserve(item_cap);
+        db
+    }
+
+    pub(super) fn item_count(&self) -> u64 {
+        self.item_count
+    }
+
+    pub(super) fn item_cap(&self) -> u64 {
+        self.blocks.len() as u64 * self.block_cap
+    }
+
+    pub(super) fn high(&self) -> u64 {
+        self.high
+    }
+
+    pub(super) fn deleted_count(&self) -> usize {
+        self.deleted.len()
+    }
+
+    // make sure items below cap are addressable
+    fn reserve(
+        &mut self,
+        cap: u64,
+    ) {
+        while self.item_cap() < cap {
+            let block = vec![0u64; self.block_cap as usize * self.stride].into_boxed_slice();
+            self.blocks.push(Box::into_raw(block).cast());
+        }
+    }
+
+    /// Make room for k more items without allocating blocks.
+    pub(super) fn accommodate(
+        &mut self,
+        k: u64,
+    ) {
+        self.reserve(self.item_count + self.deleted.len() as u64 + k);
+    }
+
+    fn header(
+        &self,
+        idx: u64,
+    ) -> *mut u64 {
+        let block = self.blocks[(idx / self.block_cap) as usize];
+        let offset = (idx % self.block_cap) as usize * self.stride;
+        unsafe { block.add(offset) }
+    }
+
+    fn item(
+        &self,
+        idx: u64,
+    ) -> *mut u8 {
+        unsafe { self.header(idx).add(1).cast() }
+    }
+
+    /// Returns true if the item was deleted, item must be allocated by a [`RawDataBlock`].
+    pub(super) unsafe fn is_deleted(item: *const u8) -> bool {
+        *item.cast::<u64>().sub(1) & DELETED_MARKER != 0
+    }
+
+    pub(super) fn allocate(&mut self) -> (u64, *mut u8) {
+        let idx = match self.deleted.pop() {
+            Some(idx) => idx,
+            None => {
+                self.reserve(self.high + 1);
+                self.high += 1;
+                self.high - 1
+            }
+        };
+        unsafe { *self.header(idx) = 0 };
+        self.item_count += 1;
+        (idx, self.item(idx))
+    }
+
+    /// Allocate a specific index, returns None if it is in use.
+    pub(super) fn allocate_at(
+        &mut self,
+        idx: u64,
+    ) -> Option<*mut u8> {
+        if idx < self.high {
+            let pos = self.deleted.iter().position(|d| *d == idx)?;
+            self.deleted.swap_remove(pos);
+        } else {
+            self.reserve(idx + 1);
+            for gap in self.high..idx {
+                unsafe { *self.header(gap) = DELETED_MARKER };
+                self.deleted.push(gap);
+            }
+            self.high = idx + 1;
+        }
+        unsafe { *self.header(idx) = 0 };
+        self.item_count += 1;
+        Some(self.item(idx))
+    }
+
+    pub(super) fn get(
+        &self,
+        idx: u64,
+    ) -> Option<*mut u8> {
+        if idx >= self.high || unsafe { *self.header(idx) } & DELETED_MARKER != 0 {
+            return None;
+        }
+        Some(self.item(idx))
+    }
+
+    /// Mark the item deleted, returns its address if it was live.
+    pub(super) fn delete(
+        &mut self,
+        idx: u64,
+    ) -> Option<*mut u8> {
+        let item = self.get(idx)?;
+        unsafe { *self.header(idx) = DELETED_MARKER };
+        self.deleted.push(idx);
+        self.item_count -= 1;
+        Some(item)
+    }
+
+    /// The first live item at or after idx.
+    pub(super) fn next_live(
+        &self,
+        mut idx: u64,
+    ) -> Option<(u64, *mut u8)> {
+        while idx < self.high {
+            if let Some(item) = self.get(idx) {
+                return Some((idx, item));
+            }
+            idx += 1;
+        }
+        None
+    }
+}
+
+/// Entity storage addressed by id.
+/// Items never move, deleted ids are reused before new ids are allocated.
+pub struct DataBlock<T> {
+    raw: RawDataBlock,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Drop for DataBlock<T> {
+    fn drop(&mut self) {
+        let mut idx = 0;
+        while let Some((id, item)) = self.raw.next_live(idx) {
+            unsafe { item.cast::<T>().drop_in_place() };
+            idx = id + 1;
+        }
+    }
+}
+
+impl<T> DataBlock<T> {
+    pub fn new(
+        block_cap: u64,
+        item_cap: u64,
+    ) -> Self {
+        assert!(align_of::<T>() <= align_of::<u64>());
+        Self {
+            raw: RawDataBlock::new(block_cap, item_cap, size_of::<T>()),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Number of live items.
+    pub fn len(&self) -> u64 {
+        self.raw.item_count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.raw.item_cap()
+    }
+
+    pub fn deleted_count(&self) -> usize {
+        self.raw.deleted_count()
+    }
+
+    pub fn accommodate(
+        &mut self,
+        k: u64,
+    ) {
+        self.raw.accommodate(k);
+    }
+
+    pub fn insert(
+        &mut self,
+        value: T,
+    ) -> u64 {
+        let (idx, item) = self.raw.allocate();
+        unsafe { item.cast::<T>().write(value) };
+        idx
+    }
+
+    /// Insert at a specific id, returns false if the id is in use.
+    pub fn insert_at(
+        &mut self,
+        id: u64,
+        value: T,
+    ) -> bool {
+        let Some(item) = self.raw.allocate_at(id) else {
+            return false;
+        };
+        unsafe { item.cast::<T>().write(value) };
+        true
+    }
+
+    pub fn get(
+        &self,
+        id: u64,
+    ) -> Option<&T> {
+        self.raw.get(id).map(|item| unsafe { &*item.cast::<T>() })
+    }
+
+    pub fn get_mut(
+        &mut self,
+        id: u64,
+    ) -> Option<&mut T> {
+        self.raw
+            .get(id)
+            .map(|item| unsafe { &mut *item.cast::<T>() })
+    }
+
+    pub fn remove(
+        &mut self,
+        id: u64,
+    ) -> Option<T> {
+        self.raw
+            .delete(id)
+            .map(|item| unsafe { item.cast::<T>().read() })
+    }
+
+    /// Returns true if the item at this address was removed.
+    ///
+    /// # Safety
+    ///
+    /// item must point into a [`DataBlock`] that is still alive.
+    pub unsafe fn is_deleted(item: *const T) -> bool {
+        RawDataBlock::is_deleted(item.cast())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &T)> {
+        let mut idx = 0;
+        std::iter::from_fn(move || {
+            let (id, item) = self.raw.next_live(idx)?;
+            idx = id + 1;
+            Some((id, unsafe { &*item.cast::<T>() }))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (u64, &mut T)> {
+        // items are disjoint and reached through the raw block pointers
+        // so handing out &mut while self is mutably borrowed is sound
+        let raw = &self.raw;
+        let mut idx = 0;
+        std::iter::from_fn(move || {
+            let (id, item) = raw.next_live(idx)?;
+            idx = id + 1;
+            Some((id, unsafe { &mut *item.cast::<T>() }))
+        })
+    }
+
+    /// Move the live items to the lowest ids keeping their order and release unused blocks.
+    /// Returns the new id of every old id, deleted ids map to u64::MAX.
+    /// Item addresses change.
+    pub fn compact(&mut self) -> Vec<u64> {
+        let mut raw = RawDataBlock::new(self.raw.block_cap, self.len(), size_of::<T>());
+        let mut map = vec![u64::MAX; self.raw.high() as usize];
+        let mut idx = 0;
+        while let Some((id, item)) = self.raw.next_live(idx) {
+            let (new_id, dst) = raw.allocate();
+            unsafe { ptr::copy_nonoverlapping(item, dst, size_of::<T>()) };
+            map[id as usize] = new_id;
+            idx = id + 1;
+        }
+        // items were moved so the old blocks are released without dropping them
+        self.raw = raw;
+        map
+    }
+
+    /// Append the block to buf, item writes a single item.
+    pub fn serialize(
+        &self,
+        buf: &mut Vec<u8>,
+        mut item: impl FnMut(&T, &mut Vec<u8>),
+    ) {
+        buf.extend_from_slice(&self.raw.high().to_le_bytes());
+        buf.extend_from_slice(&self.len().to_le_bytes());
+        for (id, v) in self.iter() {
+            buf.extend_from_slice(&id.to_le_bytes());
+            item(v, buf);
+        }
+    }
+
+    /// Read a block written by [`DataBlock::serialize`] from the start of buf,
+    /// item reads a single item and returns it with the number of bytes consumed.
+    /// Ids are preserved, returns the block and the number of bytes consumed.
+    pub fn deserialize(
+        buf: &[u8],
+        block_cap: u64,
+        mut item: impl FnMut(&[u8]) -> Option<(T, usize)>,
+    ) -> Option<(Self, usize)> {
+        let mut pos = 0;
+        let read_u64 = |pos: &mut usize| -> Option<u64> {
+            let bytes = buf.get(*pos..*pos + 8)?;
+            *pos += 8;
+            Some(u64::from_le_bytes(bytes.try_into().unwrap()))
+        };
+
+        let high = read_u64(&mut pos)?;
+        let count = read_u64(&mut pos)?;
+        if count > high {
+            return None;
+        }
+        let mut db = Self::new(block_cap, high);
+        for _ in 0..count {
+            let id = read_u64(&mut pos)?;
+            if id >= high {
+                return None;
+            }
+            let (v, n) = item(buf.get(pos..)?)?;
+            pos += n;
+            if !db.insert_at(id, v) {
+                return None;
+            }
+        }
+        // trailing deleted items keep their ids reserved
+        if high > 0 && db.raw.get(high - 1).is_none() && db.raw.allocate_at(high - 1).is_some() {
+            db.raw.delete(high - 1);
+        }
+        Some((db, pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_remove() {
+        let mut db = DataBlock::<u64>::new(4, 0);
+        let ids = (0..10).map(|i| db.insert(i * 10)).collect::<Vec<_>>();
+        assert_eq!(ids, (0..10).collect::<Vec<_>>());
+        assert_eq!(db.len(), 10);
+        assert!(db.capacity() >= 10);
+
+        let addr = db.get(3).unwrap() as *const u64;
+        assert_eq!(db.remove(3), Some(30));
+        assert_eq!(db.remove(3), None);
+        assert!(unsafe { DataBlock::is_deleted(addr) });
+        assert_eq!(db.get(3), None);
+        assert_eq!(db.deleted_count(), 1);
+
+        // deleted ids are reused and addresses are stable
+        assert_eq!(db.insert(33), 3);
+        assert_eq!(db.get(3).unwrap() as *const u64, addr);
+        assert!(!unsafe { DataBlock::is_deleted(addr) });
+
+        assert!(!db.insert_at(5, 0));
+        assert!(db.insert_at(20, 200));
+        assert_eq!(db.len(), 12);
+        assert_eq!(db.deleted_count(), 10);
+        assert_eq!(db.insert(1), 19);
+
+        *db.get_mut(0).unwrap() = 1;
+        let live = db.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(live, vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 19, 20]);
+        assert_eq!(
+            db.iter_mut().map(|(_, v)| *v).sum::<u64>(),
+            1 + 10 + 20 + 33 + 40 + 50 + 60 + 70 + 80 + 90 + 1 + 200
+        );
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut db = DataBlock::<String>::new(2, 0);
+        for i in 0..6 {
+            db.insert(i.to_string());
+        }
+        db.remove(0);
+        db.remove(3);
+        db.remove(5);
+
+        let map = db.compact();
+        assert_eq!(map, vec![u64::MAX, 0, 1, u64::MAX, 2, u64::MAX]);
+        assert_eq!(db.len(), 3);
+        assert_eq!(db.deleted_count(), 0);
+        assert!(db.capacity() < 6);
+        assert_eq!(
+            db.iter()
+                .map(|(id, s)| (id, s.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(0, "1"), (1, "2"), (2, "4")]
+        );
+        assert_eq!(db.insert("6".to_string()), 3);
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut db = DataBlock::<String>::new(2, 0);
+        for i in 0..5 {
+            db.insert(format!("item {i}"));
+        }
+        db.remove(1);
+        db.remove(4);
+
+        let mut buf = vec![];
+        db.serialize(&mut buf, |s, buf| {
+            buf.push(s.len() as u8);
+            buf.extend_from_slice(s.as_bytes());
+        });
+        let (clone, read) = DataBlock::<String>::deserialize(&buf, 2, |buf| {
+            let len = *buf.first()? as usize;
+            let s = String::from_utf8(buf.get(1..1 + len)?.to_vec()).ok()?;
+            Some((s, 1 + len))
+        })
+        .unwrap();
+        assert_eq!(read, buf.len());
+        assert_eq!(
+            clone.iter().collect::<Vec<_>>(),
+            db.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(clone.deleted_count(), 2);
+
+        assert!(DataBlock::<String>::deserialize(&buf[..buf.len() - 1], 2, |_| None).is_none());
+    }
+}
@@ -0,0 +1,211 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{ffi::c_void, ptr::null_mut};
+
+use crate::guard::ffi_guard;
+
+use super::block::RawDataBlock;
+
+type Destructor = Option<unsafe extern "C" fn(*mut c_void)>;
+
+/// Untyped items of a fixed size, cleaned up by an optional destructor.
+struct CDataBlock {
+    raw: RawDataBlock,
+    destructor: Destructor,
+}
+
+impl Drop for CDataBlock {
+    fn drop(&mut self) {
+        let Some(destructor) = self.destructor else {
+            return;
+        };
+        let mut idx = 0;
+        while let Some((id, item)) = self.raw.next_live(idx) {
+            unsafe { destructor(item.cast()) };
+            idx = id + 1;
+        }
+    }
+}
+
+type _DataBlock = *mut CDataBlock;
+
+struct DataBlockIterator {
+    db: *const CDataBlock,
+    start: u64,
+    pos: u64,
+    end: u64,
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_New(
+    block_cap: u64,
+    item_cap: u64,
+    item_size: u32,
+    destructor: Destructor,
+) -> _DataBlock {
+    ffi_guard!(null_mut(), {
+        Box::into_raw(Box::new(CDataBlock {
+            raw: RawDataBlock::new(block_cap, item_cap, item_size as usize),
+            destructor,
+        }))
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_ItemCount(db: _DataBlock) -> u64 {
+    ffi_guard!(0, { (*db).raw.item_count() })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_ItemCap(db: _DataBlock) -> u64 {
+    ffi_guard!(0, { (*db).raw.item_cap() })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_DeletedItemsCount(db: _DataBlock) -> u32 {
+    ffi_guard!(0, { (*db).raw.deleted_count() as u32 })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_Accommodate(
+    db: _DataBlock,
+    k: i64,
+) {
+    ffi_guard!((), {
+        if k > 0 {
+            (*db).raw.accommodate(k as u64);
+        }
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_AllocateItem(
+    db: _DataBlock,
+    idx: *mut u64,
+) -> *mut c_void {
+    ffi_guard!(null_mut(), {
+        let (id, item) = (*db).raw.allocate();
+        if !idx.is_null() {
+            *idx = id;
+        }
+        item.cast()
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_AllocateItemOutOfOrder(
+    db: _DataBlock,
+    idx: u64,
+) -> *mut c_void {
+    ffi_guard!(null_mut(), {
+        (*db)
+            .raw
+            .allocate_at(idx)
+            .map_or(null_mut(), |item| item.cast())
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_MarkAsDeletedOutOfOrder(
+    db: _DataBlock,
+    idx: u64,
+) {
+    ffi_guard!((), {
+        // reserve the id without running the destructor on an empty item
+        if (*db).raw.allocate_at(idx).is_some() {
+            (*db).raw.delete(idx);
+        }
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_GetItem(
+    db: _DataBlock,
+    idx: u64,
+) -> *mut c_void {
+    ffi_guard!(null_mut(), {
+        (*db).raw.get(idx).map_or(null_mut(), |item| item.cast())
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_DeleteItem(
+    db: _DataBlock,
+    idx: u64,
+) {
+    ffi_guard!((), {
+        let Some(item) = (*db).raw.get(idx) else {
+            return;
+        };
+        if let Some(destructor) = (*db).destructor {
+            destructor(item.cast());
+        }
+        (*db).raw.delete(idx);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_ItemIsDeleted(item: *mut c_void) -> bool {
+    ffi_guard!(false, { RawDataBlock::is_deleted(item.cast()) })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_Free(db: _DataBlock) {
+    ffi_guard!((), {
+        if !db.is_null() {
+            drop(Box::from_raw(db));
+        }
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlock_Scan(db: _DataBlock) -> *mut DataBlockIterator {
+    ffi_guard!(null_mut(), {
+        Box::into_raw(Box::new(DataBlockIterator {
+            db,
+            start: 0,
+            pos: 0,
+            end: (*db).raw.high(),
+        }))
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlockIterator_Next(
+    iter: *mut DataBlockIterator,
+    id: *mut u64,
+) -> *mut c_void {
+    ffi_guard!(null_mut(), {
+        let iter = &mut *iter;
+        match (*iter.db).raw.next_live(iter.pos) {
+            Some((idx, item)) if idx < iter.end => {
+                iter.pos = idx + 1;
+                if !id.is_null() {
+                    *id = idx;
+                }
+                item.cast()
+            }
+            _ => {
+                iter.pos = iter.end;
+                null_mut()
+            }
+        }
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlockIterator_Reset(iter: *mut DataBlockIterator) {
+    ffi_guard!((), { (*iter).pos = (*iter).start })
+}
+
+#[no_mangle]
+unsafe extern "C" fn DataBlockIterator_Free(iter: *mut DataBlockIterator) {
+    ffi_guard!((), {
+        if !iter.is_null() {
+            drop(Box::from_raw(iter));
+        }
+    })
+}
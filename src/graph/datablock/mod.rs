@@ -0,0 +1,11 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+mod block;
+#[cfg(feature = "rust_datablock")]
+mod ffi;
+
+#[cfg_attr(not(feature = "rust_graph"), allow(unused_imports))]
+pub use block::DataBlock;
@@ -0,0 +1,251 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{ffi::CStr, marker::PhantomData};
+
+use crate::binding::graph::*;
+
+/// Iterator over the (attribute id, value) pairs of an entity.
+/// Values are borrowed from the attribute set and must not be freed.
+pub struct Properties<'a> {
+    set: AttributeSet,
+    idx: u16,
+    count: u16,
+    phantom: PhantomData<&'a AttributeSet>,
+}
+
+impl<'a> Properties<'a> {
+    fn new(set: AttributeSet) -> Self {
+        Self {
+            set,
+            idx: 0,
+            count: unsafe { AttributeSet_Count(set) },
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a> Iterator for Properties<'a> {
+    type Item = (AttributeID, RawSIValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx >= self.count {
+            return None;
+        }
+        let mut attr_id = 0;
+        let value = unsafe { AttributeSet_GetIdx(self.set, self.idx, &mut attr_id) };
+        self.idx += 1;
+        Some((attr_id, value))
+    }
+}
+
+fn get_property<'a>(
+    attributes: *mut AttributeSet,
+    attr_id: AttributeID,
+) -> Option<&'a RawSIValue> {
+    unsafe { AttributeSet_Get(*attributes, attr_id).as_ref() }
+}
+
+/// A node of the graph, valid while the graph is borrowed.
+pub struct NodeRef<'g> {
+    graph: &'g GraphAPI,
+    node: Node,
+}
+
+impl<'g> NodeRef<'g> {
+    pub fn new(
+        graph: &'g GraphAPI,
+        id: NodeID,
+    ) -> Option<Self> {
+        graph.get_node(id).map(|node| Self { graph, node })
+    }
+
+    pub fn id(&self) -> NodeID {
+        self.node.id
+    }
+
+    pub fn labels(&self) -> Vec<LabelID> {
+        self.graph.node_labels(&self.node)
+    }
+
+    pub fn get_property(
+        &self,
+        attr_id: AttributeID,
+    ) -> Option<&RawSIValue> {
+        get_property(self.node.attributes, attr_id)
+    }
+
+    pub fn properties(&self) -> Properties<'_> {
+        Properties::new(unsafe { *self.node.attributes })
+    }
+}
+
+/// A node of the graph that can be modified, valid while the graph is mutably borrowed.
+pub struct NodeMut<'g> {
+    graph: &'g mut GraphAPI,
+    node: Node,
+}
+
+impl<'g> NodeMut<'g> {
+    pub fn new(
+        graph: &'g mut GraphAPI,
+        id: NodeID,
+    ) -> Option<Self> {
+        graph.get_node(id).map(|node| Self { graph, node })
+    }
+
+    pub fn id(&self) -> NodeID {
+        self.node.id
+    }
+
+    /// Read only access to the node.
+    pub fn as_ref(&self) -> NodeRef<'_> {
+        NodeRef {
+            graph: self.graph,
+            node: self.node,
+        }
+    }
+
+    /// Set the attribute to a clone of value, a null value removes it.
+    /// Returns true if the attribute set changed.
+    pub fn set_property(
+        &mut self,
+        attr_id: AttributeID,
+        value: RawSIValue,
+    ) -> bool {
+        unsafe { AttributeSet_Update(self.node.attributes, attr_id, value) }
+    }
+
+    pub fn as_raw(&mut self) -> *mut Node {
+        &mut self.node
+    }
+}
+
+/// An edge of the graph, valid while the graph is borrowed.
+pub struct EdgeRef<'g> {
+    graph: PhantomData<&'g GraphAPI>,
+    edge: Edge,
+}
+
+impl<'g> EdgeRef<'g> {
+    pub fn new(
+        graph: &'g GraphAPI,
+        id: EntityID,
+    ) -> Option<Self> {
+        graph
+            .get_edge(id)
+            .map(|edge| unsafe { Self::from_raw(graph, edge) })
+    }
+
+    /// Wrap an edge fetched from the graph.
+    ///
+    /// # Safety
+    ///
+    /// The edge must belong to graph and its attributes
+    /// must stay valid while the graph is borrowed.
+    pub unsafe fn from_raw(
+        _graph: &'g GraphAPI,
+        edge: Edge,
+    ) -> Self {
+        Self {
+            graph: PhantomData,
+            edge,
+        }
+    }
+
+    pub fn id(&self) -> EntityID {
+        self.edge.id
+    }
+
+    pub fn src_id(&self) -> NodeID {
+        self.edge.src_id
+    }
+
+    pub fn dest_id(&self) -> NodeID {
+        self.edge.dest_id
+    }
+
+    pub fn relation_id(&self) -> RelationID {
+        self.edge.relation_id
+    }
+
+    /// The relationship type name, None if the edge was not fetched with it.
+    pub fn relationship_type(&self) -> Option<&CStr> {
+        if self.edge.relationship.is_null() {
+            return None;
+        }
+        Some(unsafe { CStr::from_ptr(self.edge.relationship) })
+    }
+
+    pub fn get_property(
+        &self,
+        attr_id: AttributeID,
+    ) -> Option<&RawSIValue> {
+        get_property(self.edge.attributes, attr_id)
+    }
+
+    pub fn properties(&self) -> Properties<'_> {
+        Properties::new(unsafe { *self.edge.attributes })
+    }
+}
+
+/// An edge of the graph that can be modified, valid while the graph is mutably borrowed.
+pub struct EdgeMut<'g> {
+    graph: PhantomData<&'g mut GraphAPI>,
+    edge: Edge,
+}
+
+impl<'g> EdgeMut<'g> {
+    pub fn new(
+        graph: &'g mut GraphAPI,
+        id: EntityID,
+    ) -> Option<Self> {
+        graph
+            .get_edge(id)
+            .map(|edge| unsafe { Self::from_raw(graph, edge) })
+    }
+
+    /// Wrap an edge fetched from the graph.
+    ///
+    /// # Safety
+    ///
+    /// The edge must belong to graph and its attributes
+    /// must stay valid while the graph is borrowed.
+    pub unsafe fn from_raw(
+        _graph: &'g mut GraphAPI,
+        edge: Edge,
+    ) -> Self {
+        Self {
+            graph: PhantomData,
+            edge,
+        }
+    }
+
+    pub fn id(&self) -> EntityID {
+        self.edge.id
+    }
+
+    /// Read only access to the edge.
+    pub fn as_ref(&self) -> EdgeRef<'_> {
+        EdgeRef {
+            graph: PhantomData,
+            edge: self.edge,
+        }
+    }
+
+    /// Set the attribute to a clone of value, a null value removes it.
+    /// Returns true if the attribute set changed.
+    pub fn set_property(
+        &mut self,
+        attr_id: AttributeID,
+        value: RawSIValue,
+    ) -> bool {
+        unsafe { AttributeSet_Update(self.edge.attributes, attr_id, value) }
+    }
+
+    pub fn as_raw(&mut self) -> *mut Edge {
+        &mut self.edge
+    }
+}
@@ -3,17 +3,32 @@
  * Licensed under the Server Side Public License v1 (SSPLv1).
  */
 
-use std::{mem::MaybeUninit, ptr::null_mut};
+use std::{
+    mem::MaybeUninit,
+    ops::Deref,
+    ptr::null_mut,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+};
 
 use libc::pthread_mutex_t;
 
-use crate::binding::graph::{ConfigOptionField, Config_Option_get};
+use crate::{
+    binding::graph::{ConfigOptionField, Config_Option_get},
+    guard::Poison,
+};
 
 use super::{
+    delta_matrix_iter::DeltaMatrixIter,
+    reclaim,
     sparse_matrix::SparseMatrix,
+    sparse_matrix_iter::SparseMatrixIter,
     GraphBLAS::{
-        GrB_ALL, GrB_BOOL, GrB_DESC_RSC, GrB_DESC_RSCT0, GrB_DESC_RT0, GrB_DESC_S, GrB_Scalar_free,
-        GrB_Scalar_new, GrB_Semiring, GrB_Type, GxB_ANY_PAIR_BOOL, GxB_HYPERSPARSE, GxB_SPARSE,
+        GrB_ALL, GrB_BOOL, GrB_DESC_R, GrB_DESC_RSC, GrB_DESC_RSCT0, GrB_DESC_RT0, GrB_DESC_S,
+        GrB_Scalar_free, GrB_Scalar_new, GrB_Semiring, GrB_Type, GrB_UINT64, GxB_ANY_PAIR_BOOL,
+        GxB_HYPERSPARSE, GxB_SPARSE,
     },
 };
 
@@ -53,6 +68,60 @@ impl Drop for CMutex {
     }
 }
 
+/// Shares a [`DeltaMatrix`] with worker threads.
+/// GraphBLAS matrices can be read concurrently as long as no one modify them,
+/// the pending work is finished before sharing as attaching an iterator to
+/// a matrix with pending work finishes it.
+struct SharedMatrix<'a>(&'a DeltaMatrix);
+
+unsafe impl Sync for SharedMatrix<'_> {}
+
+impl<'a> SharedMatrix<'a> {
+    fn get(&self) -> &'a DeltaMatrix {
+        self.0
+    }
+}
+
+// Returns the pattern of a transposed.
+fn transpose_of(a: &SparseMatrix) -> SparseMatrix {
+    let mut t = SparseMatrix::new(unsafe { GrB_BOOL }, a.ncols(), a.nrows());
+    t.transpose(None, null_mut(), Some(a), unsafe { GrB_DESC_R });
+    t
+}
+
+// A matrix resized to the size an operation expects,
+// the matrix is copied only if its size differs.
+enum Fitted<'a> {
+    Exact(&'a SparseMatrix),
+    Resized(SparseMatrix),
+}
+
+impl Deref for Fitted<'_> {
+    type Target = SparseMatrix;
+
+    fn deref(&self) -> &SparseMatrix {
+        match self {
+            Fitted::Exact(a) => a,
+            Fitted::Resized(a) => a,
+        }
+    }
+}
+
+// Returns a with nrows and ncols, cutting a margin drops no entries
+// as no entry is set beyond the logical size.
+fn fit(
+    a: &SparseMatrix,
+    nrows: u64,
+    ncols: u64,
+) -> Fitted<'_> {
+    if a.nrows() == nrows && a.ncols() == ncols {
+        return Fitted::Exact(a);
+    }
+    let mut m = a.dup();
+    m.resize(nrows, ncols);
+    Fitted::Resized(m)
+}
+
 /// Delta Matrix solve the issue of writing to a sparse matrix with high number of nnz
 /// By using additional matrices with limited number of nnz
 /// m represent the stable matrix
@@ -60,11 +129,81 @@ impl Drop for CMutex {
 /// delta_minus recent m deletions
 pub struct DeltaMatrix {
     dirty: bool,
+    // shared with attached iterators so they detect changes and frees
+    version: Arc<AtomicU64>,
+    // logical size, the matrices may be larger
+    nrows: u64,
+    ncols: u64,
     matrix: SparseMatrix,
     delta_plus: SparseMatrix,
     delta_minus: SparseMatrix,
-    transposed: Option<Box<DeltaMatrix>>,
+    // set once so concurrent readers of a lazy transposed build it only once
+    transposed: OnceLock<Box<DeltaMatrix>>,
+    // the transposed is built on first use and can be dropped
+    lazy_transpose: bool,
+    transpose_counters: TransposeCounters,
     mutex: Option<CMutex>,
+    poisoned: AtomicBool,
+}
+
+/// Usage of a lazily built transposed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransposeStats {
+    /// Requests served by an existing transposed.
+    pub hits: u64,
+    /// Requests that built the transposed.
+    pub builds: u64,
+    /// Times the transposed was dropped.
+    pub drops: u64,
+}
+
+// Counts the usage of a lazy transposed,
+// requests may come from concurrent readers.
+#[derive(Default)]
+struct TransposeCounters {
+    hits: AtomicU64,
+    builds: AtomicU64,
+    drops: AtomicU64,
+}
+
+impl TransposeStats {
+    /// Fraction of requests served without building the transposed.
+    #[allow(dead_code)]
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.builds;
+        if total == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / total as f64
+    }
+}
+
+/// Version of a dropped [`DeltaMatrix`].
+pub(crate) const FREED_VERSION: u64 = u64::MAX;
+
+impl Drop for DeltaMatrix {
+    fn drop(&mut self) {
+        self.version.store(FREED_VERSION, Ordering::Release);
+        // freeing large matrices is slow, hand them off when async delete is on
+        // the transposed is a DeltaMatrix of its own and handed off when dropped
+        for m in [
+            &mut self.matrix,
+            &mut self.delta_plus,
+            &mut self.delta_minus,
+        ] {
+            reclaim::free(std::mem::replace(m, SparseMatrix::from(null_mut())).grb_matrix());
+        }
+    }
+}
+
+impl Poison for DeltaMatrix {
+    fn poison(&self) {
+        self.poisoned.store(true, Ordering::Relaxed);
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Relaxed)
+    }
 }
 
 impl DeltaMatrix {
@@ -78,28 +217,21 @@ impl DeltaMatrix {
         unsafe {
             let mut x = Self {
                 dirty: false,
+                version: Arc::new(AtomicU64::new(0)),
+                nrows,
+                ncols,
                 matrix: SparseMatrix::new(ty, nrows, ncols),
                 delta_plus: SparseMatrix::new(ty, nrows, ncols),
                 delta_minus: SparseMatrix::new(GrB_BOOL, nrows, ncols),
                 transposed: if transpose {
-                    let mut t = Box::new(Self {
-                        dirty: false,
-                        matrix: SparseMatrix::new(GrB_BOOL, ncols, nrows),
-                        delta_plus: SparseMatrix::new(GrB_BOOL, ncols, nrows),
-                        delta_minus: SparseMatrix::new(GrB_BOOL, ncols, nrows),
-                        transposed: None,
-                        mutex: None,
-                    });
-                    t.matrix.set_sparsity(GxB_SPARSE | GxB_HYPERSPARSE);
-                    t.delta_plus.set_sparsity(GxB_HYPERSPARSE);
-                    t.delta_plus.set_always_hyper();
-                    t.delta_minus.set_sparsity(GxB_HYPERSPARSE);
-                    t.delta_minus.set_always_hyper();
-                    Some(t)
+                    OnceLock::from(Self::new_transposed(ncols, nrows))
                 } else {
-                    None
+                    OnceLock::new()
                 },
+                lazy_transpose: false,
+                transpose_counters: TransposeCounters::default(),
                 mutex: Some(CMutex::new()),
+                poisoned: AtomicBool::new(false),
             };
             x.matrix.set_sparsity(GxB_SPARSE | GxB_HYPERSPARSE);
             x.delta_plus.set_sparsity(GxB_HYPERSPARSE);
@@ -110,9 +242,101 @@ impl DeltaMatrix {
         }
     }
 
+    /// Creates a new [`DeltaMatrix`] whose transposed is built on first use
+    /// of [`DeltaMatrix::transposed`] and maintained from then on.
+    pub fn new_lazy_transpose(
+        ty: GrB_Type,
+        nrows: u64,
+        ncols: u64,
+    ) -> Self {
+        let mut x = Self::new(ty, nrows, ncols, false);
+        x.lazy_transpose = true;
+        x
+    }
+
+    fn new_transposed(
+        nrows: u64,
+        ncols: u64,
+    ) -> Box<Self> {
+        unsafe {
+            let mut t = Box::new(Self {
+                dirty: false,
+                version: Arc::new(AtomicU64::new(0)),
+                nrows,
+                ncols,
+                matrix: SparseMatrix::new(GrB_BOOL, nrows, ncols),
+                delta_plus: SparseMatrix::new(GrB_BOOL, nrows, ncols),
+                delta_minus: SparseMatrix::new(GrB_BOOL, nrows, ncols),
+                transposed: OnceLock::new(),
+                lazy_transpose: false,
+                transpose_counters: TransposeCounters::default(),
+                mutex: None,
+                poisoned: AtomicBool::new(false),
+            });
+            t.matrix.set_sparsity(GxB_SPARSE | GxB_HYPERSPARSE);
+            t.delta_plus.set_sparsity(GxB_HYPERSPARSE);
+            t.delta_plus.set_always_hyper();
+            t.delta_minus.set_sparsity(GxB_HYPERSPARSE);
+            t.delta_minus.set_always_hyper();
+            t
+        }
+    }
+
     /// Returns the transposed of this [`DeltaMatrix`].
-    pub fn transposed(&mut self) -> Option<&mut Box<DeltaMatrix>> {
-        self.transposed.as_mut()
+    /// A lazy transposed is built from [`DeltaMatrix::export`] if missing,
+    /// concurrent readers wait for a single build.
+    pub fn transposed(&self) -> Option<&DeltaMatrix> {
+        if !self.lazy_transpose {
+            return self.transposed.get().map(Box::as_ref);
+        }
+        let mut built = false;
+        let t = self.transposed.get_or_init(|| {
+            built = true;
+            self.build_transposed()
+        });
+        let counter = if built {
+            &self.transpose_counters.builds
+        } else {
+            &self.transpose_counters.hits
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Some(t)
+    }
+
+    fn build_transposed(&self) -> Box<DeltaMatrix> {
+        let (prows, pcols) = (self.matrix.nrows(), self.matrix.ncols());
+        let mut t = Self::new_transposed(self.ncols, self.nrows);
+        t.matrix
+            .transpose(None, null_mut(), Some(&self.export()), unsafe {
+                GrB_DESC_R
+            });
+        // keep the margin of this matrix so both grow together
+        t.resize_physical(pcols, prows);
+        t
+    }
+
+    /// Drop a lazy transposed to release its memory, it is built again on next use.
+    /// Returns false if the transposed is not lazy or was not built.
+    /// References returned by [`DeltaMatrix::transposed`] must not be used after,
+    /// which the exclusive borrow enforces for Rust callers.
+    pub fn drop_transposed(&mut self) -> bool {
+        if !self.lazy_transpose || self.transposed.take().is_none() {
+            return false;
+        }
+        self.transpose_counters
+            .drops
+            .fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Returns the usage of the lazy transposed of this [`DeltaMatrix`].
+    pub fn transpose_stats(&self) -> TransposeStats {
+        let c = &self.transpose_counters;
+        TransposeStats {
+            hits: c.hits.load(Ordering::Relaxed),
+            builds: c.builds.load(Ordering::Relaxed),
+            drops: c.drops.load(Ordering::Relaxed),
+        }
     }
 
     fn set_dirty(
@@ -120,12 +344,33 @@ impl DeltaMatrix {
         dirty: bool,
     ) {
         self.dirty = dirty;
-        if let Some(t) = self.transposed.as_mut() {
+        if let Some(t) = self.transposed.get_mut() {
             t.set_dirty(dirty);
         }
     }
 
+    /// Returns the version of this [`DeltaMatrix`].
+    /// The version changes whenever the structure of the underlying matrices
+    /// may change so attached iterators can detect they are stale.
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    /// Returns the version shared with iterators,
+    /// it is set to [`FREED_VERSION`] once this [`DeltaMatrix`] is dropped.
+    pub(crate) fn version_cell(&self) -> Arc<AtomicU64> {
+        self.version.clone()
+    }
+
+    fn bump_version(&mut self) {
+        let v = (self.version() + 1) % FREED_VERSION;
+        self.version.store(v, Ordering::Release);
+    }
+
     /// Returns a reference to the m of this [`DeltaMatrix`].
+    /// m may be larger than [`DeltaMatrix::nrows`] x [`DeltaMatrix::ncols`]
+    /// as growth leaves a margin, no entry is set in the margin.
+    /// Use [`DeltaMatrix::export`] for a matrix of the logical size.
     pub fn m(&self) -> &SparseMatrix {
         &self.matrix
     }
@@ -142,12 +387,12 @@ impl DeltaMatrix {
 
     /// Returns the number of rows of this [`DeltaMatrix`].
     pub fn nrows(&self) -> u64 {
-        self.matrix.nrows()
+        self.nrows
     }
 
     /// Returns the number of columns of this [`DeltaMatrix`].
     pub fn ncols(&self) -> u64 {
-        self.matrix.ncols()
+        self.ncols
     }
 
     /// Returns the number of non zero values of this [`DeltaMatrix`].
@@ -155,18 +400,67 @@ impl DeltaMatrix {
         self.matrix.nvals() + self.delta_plus.nvals() - self.delta_minus.nvals()
     }
 
-    /// Resize the [`DeltaMatrix`].
+    /// Resize the [`DeltaMatrix`] to exactly the given dimensions.
     pub fn resize(
         &mut self,
         nrows_new: u64,
         ncols_new: u64,
     ) {
-        if let Some(t) = self.transposed.as_mut() {
-            t.resize(ncols_new, nrows_new);
+        self.resize_physical(nrows_new, ncols_new);
+        self.set_size(nrows_new, ncols_new);
+    }
+
+    fn resize_physical(
+        &mut self,
+        nrows: u64,
+        ncols: u64,
+    ) {
+        if let Some(t) = self.transposed.get_mut() {
+            t.resize_physical(ncols, nrows);
+        }
+        self.matrix.resize(nrows, ncols);
+        self.delta_plus.resize(nrows, ncols);
+        self.delta_minus.resize(nrows, ncols);
+        self.bump_version();
+    }
+
+    fn set_size(
+        &mut self,
+        nrows: u64,
+        ncols: u64,
+    ) {
+        if let Some(t) = self.transposed.get_mut() {
+            t.set_size(ncols, nrows);
+        }
+        self.nrows = nrows;
+        self.ncols = ncols;
+    }
+
+    /// Grow the logical size of this [`DeltaMatrix`],
+    /// the matrices are rounded up to a multiple of NODE_CREATION_BUFFER
+    /// so steady growth resizes them once per margin.
+    fn grow(
+        &mut self,
+        nrows: u64,
+        ncols: u64,
+    ) {
+        let (prows, pcols) = (self.matrix.nrows(), self.matrix.ncols());
+        if prows < nrows || pcols < ncols {
+            let mut buffer = MaybeUninit::<u64>::uninit();
+            unsafe {
+                Config_Option_get(ConfigOptionField::NODE_CREATION_BUFFER, buffer.as_mut_ptr())
+            };
+            let buffer = unsafe { buffer.assume_init() }.max(1);
+            let round = |req: u64, cap: u64| {
+                if cap < req {
+                    req.next_multiple_of(buffer)
+                } else {
+                    cap
+                }
+            };
+            self.resize_physical(round(nrows, prows), round(ncols, pcols));
         }
-        self.matrix.resize(nrows_new, ncols_new);
-        self.delta_plus.resize(nrows_new, ncols_new);
-        self.delta_minus.resize(nrows_new, ncols_new);
+        self.set_size(nrows, ncols);
     }
 
     /// Remove element from this [`DeltaMatrix`].
@@ -175,7 +469,7 @@ impl DeltaMatrix {
         i: u64,
         j: u64,
     ) {
-        if let Some(t) = self.transposed.as_mut() {
+        if let Some(t) = self.transposed.get_mut() {
             t.remove_element(j, i);
         }
 
@@ -188,13 +482,97 @@ impl DeltaMatrix {
         self.set_dirty(true);
     }
 
+    // the value at (i, j), elements of a matrix not of UINT64 type read as 1
+    #[cfg_attr(not(feature = "rust_graph"), allow(dead_code))]
+    fn extract_element(
+        &self,
+        i: u64,
+        j: u64,
+    ) -> Option<u64> {
+        if self.matrix.ty() == unsafe { GrB_UINT64 } {
+            self.extract_element_u64(i, j)
+        } else {
+            self.extract_element_bool(i, j).map(|_| 1)
+        }
+    }
+
+    /// Remove every element in row i of this [`DeltaMatrix`].
+    /// Returns the removed columns with their values,
+    /// values of a matrix not of UINT64 type are 1.
+    #[cfg_attr(not(feature = "rust_graph"), allow(dead_code))]
+    pub fn remove_row(
+        &mut self,
+        i: u64,
+    ) -> Vec<(u64, u64)> {
+        if i >= self.nrows() {
+            return vec![];
+        }
+
+        let mut cols = vec![];
+        let mut it = DeltaMatrixIter::new_range(self, i, i);
+        while let Ok(Some((_, j))) = it.next_bool() {
+            cols.push(j);
+        }
+
+        let mut removed = Vec::with_capacity(cols.len());
+        for j in cols {
+            if let Some(v) = self.extract_element(i, j) {
+                self.remove_element(i, j);
+                removed.push((j, v));
+            }
+        }
+        removed
+    }
+
+    /// Remove every element in column j of this [`DeltaMatrix`].
+    /// Returns the removed rows with their values,
+    /// values of a matrix not of UINT64 type are 1.
+    /// Proportional to the column size when there is a transposed,
+    /// otherwise the whole matrix is scanned.
+    #[cfg_attr(not(feature = "rust_graph"), allow(dead_code))]
+    pub fn remove_col(
+        &mut self,
+        j: u64,
+    ) -> Vec<(u64, u64)> {
+        if j >= self.ncols() {
+            return vec![];
+        }
+
+        let mut rows = vec![];
+        match self.transposed.get().map(Box::as_ref) {
+            Some(t) => {
+                let mut it = DeltaMatrixIter::new_range(t, j, j);
+                while let Ok(Some((_, i))) = it.next_bool() {
+                    rows.push(i);
+                }
+            }
+            None => {
+                let mut it = DeltaMatrixIter::new_range(self, 0, u64::MAX);
+                while let Ok(Some((i, col))) = it.next_bool() {
+                    if col == j {
+                        rows.push(i);
+                    }
+                }
+            }
+        }
+
+        let mut removed = Vec::with_capacity(rows.len());
+        for i in rows {
+            if let Some(v) = self.extract_element(i, j) {
+                self.remove_element(i, j);
+                removed.push((i, v));
+            }
+        }
+        removed
+    }
+
     /// Sets the element of this [`DeltaMatrix`].
     pub fn set_element_bool(
         &mut self,
         i: u64,
         j: u64,
     ) {
-        if let Some(t) = self.transposed.as_mut() {
+        if let Some(t) = self.transposed.get_mut() {
             t.set_element_bool(j, i);
         }
 
@@ -216,7 +594,7 @@ impl DeltaMatrix {
         i: u64,
         j: u64,
     ) {
-        if let Some(t) = self.transposed.as_mut() {
+        if let Some(t) = self.transposed.get_mut() {
             t.set_element_bool(j, i);
         }
 
@@ -275,44 +653,51 @@ impl DeltaMatrix {
         &mut self,
         mask: &SparseMatrix,
     ) {
-        debug_assert!(self.transposed.is_none());
+        if let Some(t) = self.transposed.get_mut() {
+            t.remove_elements(&transpose_of(mask));
+        }
 
+        let mask = fit(mask, self.matrix.nrows(), self.matrix.ncols());
         unsafe {
             let mut s = MaybeUninit::uninit();
             GrB_Scalar_new(s.as_mut_ptr(), GrB_BOOL);
             // delete all presented elements in dp
             self.delta_plus.assign_scalar(
-                mask,
+                &mask,
                 s.assume_init(),
                 GrB_ALL,
-                self.nrows(),
+                self.matrix.nrows(),
                 GrB_ALL,
-                self.ncols(),
+                self.matrix.ncols(),
                 GrB_DESC_S,
             );
             // delete elements presented in m  by marking them as deleted in dm
             self.delta_minus.assign(
-                mask,
+                &mask,
                 &self.matrix,
                 GrB_ALL,
-                self.nrows(),
+                self.matrix.nrows(),
                 GrB_ALL,
-                self.ncols(),
+                self.matrix.ncols(),
                 GrB_DESC_S,
             );
             self.set_dirty(true);
+            self.bump_version();
             GrB_Scalar_free(s.as_mut_ptr());
         }
     }
 
     /// Clear this [`DeltaMatrix`].
     pub fn clear(&mut self) {
-        debug_assert!(self.transposed.is_none());
+        if let Some(t) = self.transposed.get_mut() {
+            t.clear();
+        }
 
         self.matrix.clear();
         self.delta_plus.clear();
         self.delta_minus.clear();
         self.set_dirty(true);
+        self.bump_version();
     }
 
     /// Copy this [`DeltaMatrix`].
@@ -320,11 +705,75 @@ impl DeltaMatrix {
         &mut self,
         a: &DeltaMatrix,
     ) {
-        debug_assert!(self.transposed.is_none());
+        // take the size and the margin of a so the matrices can be copied,
+        // the transposed takes them transposed
+        let (prows, pcols) = (a.matrix.nrows(), a.matrix.ncols());
+        if let Some(t) = self.transposed.get_mut() {
+            match a.transposed.get().map(Box::as_ref) {
+                Some(at) => t.copy(at),
+                None => {
+                    t.clear();
+                    t.resize_physical(pcols, prows);
+                    t.set_size(a.ncols, a.nrows);
+                    t.matrix
+                        .copy(&fit(&transpose_of(&a.export()), pcols, prows));
+                    t.bump_version();
+                }
+            }
+        }
+
+        self.matrix.resize(prows, pcols);
+        self.delta_plus.resize(prows, pcols);
+        self.delta_minus.resize(prows, pcols);
+        self.nrows = a.nrows;
+        self.ncols = a.ncols;
 
         self.matrix.copy(&a.matrix);
         self.delta_plus.copy(&a.delta_plus);
         self.delta_minus.copy(&a.delta_minus);
+        self.bump_version();
+    }
+
+    /// Keep only the given rows and columns of this [`DeltaMatrix`],
+    /// row rows\[i\] moves to i and column cols\[j\] moves to j.
+    /// Pending changes are flushed first.
+    #[cfg_attr(not(feature = "rust_graph"), allow(dead_code))]
+    pub fn permute(
+        &mut self,
+        rows: &[u64],
+        cols: &[u64],
+    ) {
+        self.wait(true);
+        if let Some(t) = self.transposed.get_mut() {
+            t.permute(cols, rows);
+        }
+
+        let (nrows, ncols) = (rows.len() as u64, cols.len() as u64);
+        let mut m = SparseMatrix::new(self.matrix.ty(), nrows, ncols);
+        m.set_sparsity(GxB_SPARSE | GxB_HYPERSPARSE);
+        m.extract(&self.matrix, rows, cols);
+        self.matrix = m;
+        self.delta_plus.resize(nrows, ncols);
+        self.delta_minus.resize(nrows, ncols);
+        self.nrows = nrows;
+        self.ncols = ncols;
+        self.bump_version();
+    }
+
+    /// Replace every value v of this [`DeltaMatrix`] with f(v).
+    #[cfg_attr(not(feature = "rust_graph"), allow(dead_code))]
+    pub fn map_values(
+        &mut self,
+        f: impl Fn(u64) -> u64,
+    ) {
+        let mut entries = vec![];
+        let mut it = DeltaMatrixIter::new_range(self, 0, u64::MAX);
+        while let Ok(Some((i, j, v))) = it.next::<u64>() {
+            entries.push((i, j, v));
+        }
+        for (i, j, v) in entries {
+            self.set_element_u64(f(v), i, j);
+        }
     }
 
     /// Multiply m by n and the result is in this [`DeltaMatrix`].
@@ -334,13 +783,15 @@ impl DeltaMatrix {
         m: &DeltaMatrix,
         n: &DeltaMatrix,
     ) {
+        let (nrows, ncols) = (self.nrows, self.ncols);
+        let mm = m.logical(&m.matrix);
         unsafe {
             let (mask, desc) = if n.delta_minus.nvals() > 0 {
-                let mut mask = SparseMatrix::new(GrB_BOOL, self.nrows(), self.ncols());
+                let mut mask = SparseMatrix::new(GrB_BOOL, nrows, ncols);
                 mask.mxm(
                     None,
-                    &m.matrix,
-                    &n.delta_minus,
+                    &mm,
+                    &n.logical(&n.delta_minus),
                     GxB_ANY_PAIR_BOOL,
                     null_mut(),
                 );
@@ -354,8 +805,8 @@ impl DeltaMatrix {
             };
 
             let accum = if n.delta_plus.nvals() > 0 {
-                let mut accum = SparseMatrix::new(GrB_BOOL, self.nrows(), self.ncols());
-                accum.mxm(None, &m.matrix, &n.delta_plus, semiring, null_mut());
+                let mut accum = SparseMatrix::new(GrB_BOOL, nrows, ncols);
+                accum.mxm(None, &mm, &n.logical(&n.delta_plus), semiring, null_mut());
                 if accum.nvals() > 0 {
                     Some(accum)
                 } else {
@@ -365,12 +816,13 @@ impl DeltaMatrix {
                 None
             };
 
-            self.matrix
-                .mxm(mask.as_ref(), &m.matrix, &n.matrix, semiring, desc);
-            if let Some(accum) = accum {
-                self.matrix
-                    .element_wise_add(None, None, Some(&accum), GxB_ANY_PAIR_BOOL);
-            }
+            let nm = n.logical(&n.matrix);
+            self.with_logical_m(|matrix| {
+                matrix.mxm(mask.as_ref(), &mm, &nm, semiring, desc);
+                if let Some(accum) = accum {
+                    matrix.element_wise_add(None, None, Some(&accum), GxB_ANY_PAIR_BOOL);
+                }
+            });
         }
     }
 
@@ -381,32 +833,52 @@ impl DeltaMatrix {
         m: &DeltaMatrix,
         n: &DeltaMatrix,
     ) {
-        match (
-            m.delta_minus.nvals() > 0 || m.delta_plus.nvals() > 0,
-            n.delta_minus.nvals() > 0 || n.delta_plus.nvals() > 0,
-        ) {
-            (true, true) => {
-                self.matrix
-                    .element_wise_add(None, Some(&m.export()), Some(&n.export()), semiring);
-            }
-            (true, false) => {
-                self.matrix
-                    .element_wise_add(None, Some(&m.export()), Some(&n.matrix), semiring);
-            }
-            (false, true) => {
-                self.matrix
-                    .element_wise_add(None, Some(&m.matrix), Some(&n.export()), semiring);
-            }
-            (false, false) => {
-                self.matrix
-                    .element_wise_add(None, Some(&m.matrix), Some(&n.matrix), semiring);
+        fn operand(a: &DeltaMatrix) -> Fitted<'_> {
+            if a.delta_minus.nvals() > 0 || a.delta_plus.nvals() > 0 {
+                Fitted::Resized(a.export())
+            } else {
+                a.logical(&a.matrix)
             }
         }
+        let (m, n) = (operand(m), operand(n));
+        self.with_logical_m(|matrix| {
+            matrix.element_wise_add(None, Some(&m), Some(&n), semiring);
+        });
+    }
+
+    // Returns a, one of the matrices of this [`DeltaMatrix`], at the logical size.
+    fn logical<'a>(
+        &self,
+        a: &'a SparseMatrix,
+    ) -> Fitted<'a> {
+        fit(a, self.nrows, self.ncols)
+    }
+
+    // Run f on m cut to the logical size, the margin is restored after.
+    fn with_logical_m(
+        &mut self,
+        f: impl FnOnce(&mut SparseMatrix),
+    ) {
+        let (prows, pcols) = (self.matrix.nrows(), self.matrix.ncols());
+        let margin = prows != self.nrows || pcols != self.ncols;
+        if margin {
+            self.matrix.resize(self.nrows, self.ncols);
+        }
+        f(&mut self.matrix);
+        if margin {
+            self.matrix.resize(prows, pcols);
+        }
+        self.bump_version();
     }
 
-    /// Returns [`SparseMatrix`] by computing m-dm+dp of this [`DeltaMatrix`].
+    /// Returns [`SparseMatrix`] by computing m-dm+dp of this [`DeltaMatrix`],
+    /// the result has the logical size of this [`DeltaMatrix`].
     pub fn export(&self) -> SparseMatrix {
-        let mut m = SparseMatrix::new(unsafe { GrB_BOOL }, self.nrows(), self.ncols());
+        let mut m = SparseMatrix::new(
+            unsafe { GrB_BOOL },
+            self.matrix.nrows(),
+            self.matrix.ncols(),
+        );
         if self.delta_minus.nvals() > 0 {
             m.transpose(
                 Some(&self.delta_minus),
@@ -424,6 +896,9 @@ impl DeltaMatrix {
                 GxB_ANY_PAIR_BOOL
             });
         }
+        if m.nrows() != self.nrows || m.ncols() != self.ncols {
+            m.resize(self.nrows, self.ncols);
+        }
 
         m
     }
@@ -432,7 +907,7 @@ impl DeltaMatrix {
     pub fn pending(&self) -> bool {
         if self
             .transposed
-            .as_ref()
+            .get()
             .map(|t| t.pending())
             .unwrap_or_default()
         {
@@ -449,7 +924,7 @@ impl DeltaMatrix {
         &mut self,
         force_sync: bool,
     ) {
-        if let Some(t) = self.transposed.as_mut() {
+        if let Some(t) = self.transposed.get_mut() {
             t.wait(force_sync);
         }
 
@@ -464,6 +939,7 @@ impl DeltaMatrix {
 
         self.sync(force_sync, delta_max_pending_changes);
         self.set_dirty(false);
+        self.bump_version();
     }
 
     fn sync(
@@ -471,18 +947,26 @@ impl DeltaMatrix {
         force_sync: bool,
         delta_max_pending_changes: u64,
     ) {
+        let deletions = self.delta_minus.nvals();
+        let additions = self.delta_plus.nvals();
         if force_sync {
             self.sync_deletions();
             self.sync_additions();
         } else {
-            if self.delta_minus.nvals() > delta_max_pending_changes {
+            if deletions > delta_max_pending_changes {
                 self.sync_deletions();
             }
 
-            if self.delta_plus.nvals() > delta_max_pending_changes {
+            if additions > delta_max_pending_changes {
                 self.sync_additions();
             }
         }
+        log::trace!(
+            "sync force={force_sync} max_pending={delta_max_pending_changes} \
+             additions={additions} deletions={deletions} flushed_additions={} flushed_deletions={}",
+            force_sync || additions > delta_max_pending_changes,
+            force_sync || deletions > delta_max_pending_changes,
+        );
 
         self.matrix.wait();
         self.delta_plus.wait();
@@ -495,6 +979,7 @@ impl DeltaMatrix {
                 GrB_DESC_RSCT0
             });
         self.delta_minus.clear();
+        self.bump_version();
     }
 
     fn sync_additions(&mut self) {
@@ -503,13 +988,94 @@ impl DeltaMatrix {
                 &self.delta_plus,
                 &self.delta_plus,
                 GrB_ALL,
-                self.nrows(),
+                self.matrix.nrows(),
                 GrB_ALL,
-                self.ncols(),
+                self.matrix.ncols(),
                 GrB_DESC_S,
             );
         }
         self.delta_plus.clear();
+        self.bump_version();
+    }
+
+    // finish the pending work of m, dp and dm so readers do not modify them
+    fn finish_pending(&mut self) {
+        self.matrix.wait();
+        self.delta_plus.wait();
+        self.delta_minus.wait();
+    }
+
+    /// Split the rows of this [`DeltaMatrix`] into at most n contiguous ranges
+    /// with balanced number of entries.
+    /// The ranges are inclusive and cover all the rows of the matrix.
+    pub fn partition(
+        &mut self,
+        n: u64,
+    ) -> Vec<(u64, u64)> {
+        self.finish_pending();
+        self.split_rows(n)
+    }
+
+    fn split_rows(
+        &self,
+        n: u64,
+    ) -> Vec<(u64, u64)> {
+        let nrows = self.nrows();
+        if n == 0 || nrows == 0 {
+            return vec![];
+        }
+
+        // deletions are ignored as they are bounded by the entries in m
+        let mut rows = SparseMatrixIter::row_nvals(&self.matrix);
+        rows.extend(SparseMatrixIter::row_nvals(&self.delta_plus));
+        rows.sort_unstable();
+
+        let total = rows.iter().map(|(_, count)| count).sum::<u64>();
+        let target = total.div_ceil(n).max(1);
+
+        let mut ranges = Vec::with_capacity(n as usize);
+        let mut start = 0;
+        let mut acc = 0;
+        for (row, count) in rows {
+            if row < start {
+                continue;
+            }
+            acc += count;
+            if acc >= target && (ranges.len() as u64) + 1 < n {
+                ranges.push((start, row));
+                start = row + 1;
+                acc = 0;
+            }
+        }
+
+        if start < nrows {
+            ranges.push((start, nrows - 1));
+        }
+
+        ranges
+    }
+
+    /// Iterate this [`DeltaMatrix`] using nthreads workers
+    /// each worker gets a [`DeltaMatrixIter`] over a different partition.
+    pub fn par_iter<'a, F>(
+        &'a mut self,
+        nthreads: u64,
+        f: F,
+    ) where
+        F: Fn(DeltaMatrixIter<'a>) + Sync,
+    {
+        self.finish_pending();
+        let this: &'a Self = self;
+        debug_assert!(!this.matrix.pending() && !this.delta_plus.pending());
+        debug_assert!(!this.delta_minus.pending());
+        let shared = SharedMatrix(this);
+        let shared = &shared;
+        let f = &f;
+        std::thread::scope(|s| {
+            for (min_row, max_row) in this.split_rows(nthreads) {
+                s.spawn(move || f(DeltaMatrixIter::new_range(shared.get(), min_row, max_row)));
+            }
+        });
     }
 
     /// Check if need to resize or to apply pending changes on this [`DeltaMatrix`].
@@ -525,7 +1091,7 @@ impl DeltaMatrix {
         self.mutex.as_mut().unwrap().lock();
 
         if self.nrows() < nrows || self.ncols() < ncols {
-            self.resize(nrows, ncols);
+            self.grow(nrows.max(self.nrows()), ncols.max(self.ncols()));
         }
 
         if self.dirty {
@@ -538,22 +1104,31 @@ impl DeltaMatrix {
 
 #[cfg(test)]
 mod tests {
-    use std::ptr::null_mut;
+    use std::{
+        ffi::CString,
+        mem::MaybeUninit,
+        ptr::null_mut,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Mutex, MutexGuard, PoisonError,
+        },
+    };
 
     use libc::{c_char, rand};
 
     use crate::{
-        binding::graph::{ConfigOptionField, Config_Option_set},
+        binding::graph::{ConfigOptionField, Config_Option_get, Config_Option_set},
         graph::matrix::{
+            reclaim,
             sparse_matrix::SparseMatrix,
             GraphBLAS::{
-                GrB_BOOL, GrB_LAND, GrB_Matrix_eWiseMult_BinaryOp, GrB_Mode, GrB_init,
+                GrB_BOOL, GrB_LAND, GrB_Matrix_eWiseMult_BinaryOp, GrB_Mode, GrB_UINT64, GrB_init,
                 GxB_ANY_PAIR_BOOL, GxB_Format_Value, GxB_Global_Option_set, GxB_Option_Field,
             },
         },
     };
 
-    use super::DeltaMatrix;
+    use super::{transpose_of, DeltaMatrix, DeltaMatrixIter, SharedMatrix, TransposeStats};
 
     fn test_init() {
         unsafe {
@@ -567,12 +1142,79 @@ mod tests {
         };
     }
 
+    // tests changing the global config run one at a time
+    // and restore the previous value when the guard is dropped
+    static CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+    struct ConfigGuard {
+        restore: Box<dyn Fn()>,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl Drop for ConfigGuard {
+        fn drop(&mut self) {
+            (self.restore)();
+        }
+    }
+
+    fn config_set(
+        field: ConfigOptionField,
+        value: &str,
+    ) {
+        let value = CString::new(value).unwrap();
+        unsafe {
+            assert!(Config_Option_set(field, value.as_ptr(), null_mut()));
+        }
+    }
+
+    fn set_node_creation_buffer(buffer: u64) -> ConfigGuard {
+        let lock = CONFIG_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut previous = MaybeUninit::<u64>::uninit();
+        let previous = unsafe {
+            assert!(Config_Option_get(
+                ConfigOptionField::NODE_CREATION_BUFFER,
+                previous.as_mut_ptr()
+            ));
+            previous.assume_init()
+        };
+        config_set(ConfigOptionField::NODE_CREATION_BUFFER, &buffer.to_string());
+        ConfigGuard {
+            restore: Box::new(move || {
+                config_set(
+                    ConfigOptionField::NODE_CREATION_BUFFER,
+                    &previous.to_string(),
+                );
+            }),
+            _lock: lock,
+        }
+    }
+
+    fn set_async_delete(async_delete: bool) -> ConfigGuard {
+        let lock = CONFIG_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut previous = MaybeUninit::<bool>::uninit();
+        let previous = unsafe {
+            assert!(Config_Option_get(
+                ConfigOptionField::ASYNC_DELETE,
+                previous.as_mut_ptr()
+            ));
+            previous.assume_init()
+        };
+        let value = |v: bool| if v { "yes" } else { "no" };
+        config_set(ConfigOptionField::ASYNC_DELETE, value(async_delete));
+        ConfigGuard {
+            restore: Box::new(move || {
+                config_set(ConfigOptionField::ASYNC_DELETE, value(previous));
+            }),
+            _lock: lock,
+        }
+    }
+
     #[test]
     fn test_new_matrix() {
         test_init();
         let nrows = 100;
         let ncols = 100;
-        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, nrows, ncols, false);
+        let a = DeltaMatrix::new(unsafe { GrB_BOOL }, nrows, ncols, false);
         assert_eq!(a.m().nvals(), 0);
         assert_eq!(a.delta_plus.nvals(), 0);
         assert_eq!(a.delta_minus.nvals(), 0);
@@ -582,7 +1224,7 @@ mod tests {
         assert!(!a.dirty);
         assert!(a.transposed().is_none());
 
-        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, nrows, ncols, true);
+        let a = DeltaMatrix::new(unsafe { GrB_BOOL }, nrows, ncols, true);
         assert_eq!(a.m().nvals(), 0);
         assert_eq!(a.delta_plus.nvals(), 0);
         assert_eq!(a.delta_minus.nvals(), 0);
@@ -702,6 +1344,77 @@ mod tests {
         assert_eq!(a.delta_plus.nvals(), 0);
     }
 
+    #[test]
+    fn test_remove_row_col() {
+        test_init();
+        let n = 10;
+        for transpose in [false, true] {
+            let mut a = DeltaMatrix::new(unsafe { GrB_UINT64 }, n, n, transpose);
+            for i in 0..n {
+                a.set_element_u64(i * n + 3, i, 3);
+                a.set_element_u64(i * n + i, i, i);
+            }
+            // mix flushed and pending entries
+            a.wait(true);
+            a.set_element_u64(3 * n + 7, 3, 7);
+
+            let mut row = a.remove_row(3);
+            row.sort_unstable();
+            assert_eq!(row, vec![(3, 3 * n + 3), (7, 3 * n + 7)]);
+            assert_eq!(a.nvals(), 2 * n - 2);
+
+            let mut col = a.remove_col(3);
+            col.sort_unstable();
+            let expected = (0..n)
+                .filter(|i| *i != 3)
+                .map(|i| (i, i * n + 3))
+                .collect::<Vec<_>>();
+            assert_eq!(col, expected);
+            assert_eq!(a.nvals(), n - 1);
+            assert!(a.remove_row(3).is_empty());
+            assert!(a.remove_col(n).is_empty());
+
+            if let Some(t) = a.transposed() {
+                assert_eq!(t.nvals(), n - 1);
+            }
+            a.wait(true);
+            for i in 0..n {
+                assert_eq!(a.extract_element_u64(i, 3), None);
+                assert_eq!(a.extract_element_u64(i, i), (i != 3).then_some(i * n + i));
+            }
+        }
+    }
+
+    #[test]
+    fn test_remove_row_col_bool() {
+        test_init();
+        let n = 10;
+        for transpose in [false, true] {
+            let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, n, n, transpose);
+            for i in 0..n {
+                a.set_element_bool(i, 3);
+                a.set_element_bool(3, i);
+            }
+            a.wait(true);
+            a.remove_element(3, 0);
+
+            let mut row = a.remove_row(3);
+            row.sort_unstable();
+            assert_eq!(row, (1..n).map(|j| (j, 1)).collect::<Vec<_>>());
+
+            let mut col = a.remove_col(3);
+            col.sort_unstable();
+            assert_eq!(
+                col,
+                (0..n)
+                    .filter(|i| *i != 3)
+                    .map(|i| (i, 1))
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(a.nvals(), 0);
+        }
+    }
+
     #[test]
     fn test_transpose() {
         test_init();
@@ -714,7 +1427,7 @@ mod tests {
 
         a.set_element_bool(i, j);
 
-        let t = a.transposed.as_ref().unwrap();
+        let t = a.transposed.get().unwrap();
 
         assert_eq!(t.extract_element_bool(j, i), Some(true));
         assert_eq!(t.nvals(), 1);
@@ -725,7 +1438,7 @@ mod tests {
 
         a.wait(true);
 
-        let t = a.transposed.as_ref().unwrap();
+        let t = a.transposed.get().unwrap();
 
         assert_eq!(t.m().nvals(), 1);
         assert_eq!(t.delta_minus.nvals(), 0);
@@ -733,7 +1446,7 @@ mod tests {
 
         a.remove_element(i, j);
 
-        let t = a.transposed.as_ref().unwrap();
+        let t = a.transposed.get().unwrap();
 
         assert!(t.dirty);
         assert_eq!(t.m().nvals(), 1);
@@ -742,7 +1455,7 @@ mod tests {
 
         a.wait(true);
 
-        let t = a.transposed.as_ref().unwrap();
+        let t = a.transposed.get().unwrap();
 
         assert_eq!(t.m().nvals(), 0);
         assert_eq!(t.delta_minus.nvals(), 0);
@@ -805,7 +1518,85 @@ mod tests {
         a.wait(true);
 
         matrix_eq(&a.matrix, &m);
-        matrix_eq(&a.transposed.unwrap().matrix, &t);
+        matrix_eq(&a.transposed.get().unwrap().matrix, &t);
+    }
+
+    fn random_fill(
+        a: &mut DeltaMatrix,
+        count: u64,
+    ) {
+        for _ in 0..count {
+            let i = unsafe { rand() as u64 } % a.nrows();
+            let j = unsafe { rand() as u64 } % a.ncols();
+            a.set_element_bool(i, j);
+        }
+    }
+
+    fn assert_transposed(a: &mut DeltaMatrix) {
+        let expected = transpose_of(&a.export());
+        let t = a.transposed().unwrap();
+        assert_eq!((t.nrows(), t.ncols()), (a.ncols(), a.nrows()));
+        assert_eq!(
+            (t.matrix.nrows(), t.matrix.ncols()),
+            (a.matrix.ncols(), a.matrix.nrows())
+        );
+        matrix_eq(&t.export(), &expected);
+    }
+
+    #[test]
+    fn test_bulk_transposed() {
+        test_init();
+        let n = 100;
+        for lazy in [false, true] {
+            let mut a = if lazy {
+                DeltaMatrix::new_lazy_transpose(unsafe { GrB_BOOL }, n, n)
+            } else {
+                DeltaMatrix::new(unsafe { GrB_BOOL }, n, n, true)
+            };
+            a.transposed();
+            random_fill(&mut a, 1000);
+            a.wait(true);
+            random_fill(&mut a, 200);
+            assert_transposed(&mut a);
+
+            let mut mask = SparseMatrix::new(unsafe { GrB_BOOL }, n, n);
+            for _ in 0..500 {
+                let i = unsafe { rand() as u64 } % n;
+                let j = unsafe { rand() as u64 } % n;
+                mask.set_element_bool(true, i, j);
+            }
+            a.remove_elements(&mask);
+            assert_transposed(&mut a);
+            a.wait(true);
+            assert_transposed(&mut a);
+            let mut it = DeltaMatrixIter::new(&a);
+            while let Ok(Some((i, j))) = it.next_bool() {
+                assert!(mask.extract_element_bool(i, j).is_none());
+            }
+
+            // copy from a larger grown matrix without a transposed
+            let mut b = DeltaMatrix::new(unsafe { GrB_BOOL }, n, n / 2, false);
+            b.synchronize(n + 50, n / 2 + 10);
+            random_fill(&mut b, 300);
+            b.wait(true);
+            random_fill(&mut b, 50);
+            a.copy(&b);
+            matrix_eq(&a.export(), &b.export());
+            assert_transposed(&mut a);
+
+            // copy from a smaller matrix with a transposed
+            let mut c = DeltaMatrix::new(unsafe { GrB_BOOL }, n / 2, n, true);
+            random_fill(&mut c, 300);
+            a.copy(&c);
+            matrix_eq(&a.export(), &c.export());
+            assert_transposed(&mut a);
+
+            a.clear();
+            assert_eq!(a.nvals(), 0);
+            assert_eq!(a.transposed().unwrap().nvals(), 0);
+            random_fill(&mut a, 10);
+            assert_transposed(&mut a);
+        }
     }
 
     #[test]
@@ -899,6 +1690,192 @@ mod tests {
         matrix_eq(&c.matrix, &d.matrix);
     }
 
+    #[test]
+    fn test_synchronize_margin() {
+        test_init();
+        let _config = set_node_creation_buffer(128);
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, 10, 10, true);
+
+        a.synchronize(15, 15);
+        assert_eq!((a.nrows(), a.ncols()), (15, 15));
+        assert_eq!((a.matrix.nrows(), a.matrix.ncols()), (128, 128));
+
+        // within the margin the matrices are not resized
+        let version = a.version();
+        a.synchronize(100, 100);
+        assert_eq!((a.nrows(), a.ncols()), (100, 100));
+        assert_eq!(a.version(), version);
+
+        a.synchronize(200, 20);
+        assert_eq!((a.nrows(), a.ncols()), (200, 100));
+        assert_eq!((a.matrix.nrows(), a.matrix.ncols()), (256, 128));
+        assert_eq!((a.delta_minus.nrows(), a.delta_plus.ncols()), (256, 128));
+        let t = a.transposed.get().unwrap();
+        assert_eq!((t.nrows(), t.ncols()), (100, 200));
+        assert_eq!((t.matrix.nrows(), t.matrix.ncols()), (128, 256));
+
+        // only an explicit resize shrinks
+        a.synchronize(50, 50);
+        assert_eq!((a.nrows(), a.ncols()), (200, 100));
+        a.resize(50, 50);
+        assert_eq!((a.nrows(), a.ncols()), (50, 50));
+        assert_eq!((a.matrix.nrows(), a.matrix.ncols()), (50, 50));
+    }
+
+    #[test]
+    fn test_grown_operands() {
+        test_init();
+        let _config = set_node_creation_buffer(128);
+        // a has a margin while b is exactly sized
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, 10, 10, false);
+        a.synchronize(20, 20);
+        assert_eq!((a.matrix.nrows(), a.matrix.ncols()), (128, 128));
+        let mut b = DeltaMatrix::new(unsafe { GrB_BOOL }, 20, 20, false);
+
+        a.set_element_bool(0, 1);
+        a.set_element_bool(2, 3);
+        a.set_element_bool(4, 5);
+        b.set_element_bool(1, 2);
+        b.set_element_bool(3, 4);
+        a.wait(true);
+        b.wait(true);
+        // mxm expects the changes of its left operand to be applied
+        b.remove_element(3, 4);
+        b.set_element_bool(5, 6);
+
+        let export = a.export();
+        assert_eq!((export.nrows(), export.ncols()), (20, 20));
+
+        let mut c = DeltaMatrix::new(unsafe { GrB_BOOL }, 20, 20, false);
+        c.mxm(unsafe { GxB_ANY_PAIR_BOOL }, &a, &b);
+        assert_eq!(c.nvals(), 2);
+        assert_eq!(c.extract_element_bool(0, 2), Some(true));
+        assert_eq!(c.extract_element_bool(4, 6), Some(true));
+
+        // the result keeps its margin
+        let mut d = DeltaMatrix::new(unsafe { GrB_BOOL }, 10, 10, false);
+        d.synchronize(20, 20);
+        d.mxm(unsafe { GxB_ANY_PAIR_BOOL }, &a, &b);
+        assert_eq!((d.matrix.nrows(), d.matrix.ncols()), (128, 128));
+        matrix_eq(&c.export(), &d.export());
+
+        d.element_wise_add(unsafe { GxB_ANY_PAIR_BOOL }, &a, &b);
+        assert_eq!(d.nvals(), 5);
+        assert_eq!(d.extract_element_bool(3, 4), None);
+
+        // copy takes the size of the source
+        let mut e = DeltaMatrix::new(unsafe { GrB_BOOL }, 5, 5, false);
+        e.copy(&a);
+        assert_eq!((e.nrows(), e.ncols()), (20, 20));
+        matrix_eq(&e.export(), &a.export());
+        a.copy(&b);
+        assert_eq!((a.nrows(), a.ncols()), (20, 20));
+        assert_eq!((a.matrix.nrows(), a.matrix.ncols()), (20, 20));
+        matrix_eq(&a.export(), &b.export());
+    }
+
+    #[test]
+    fn test_lazy_transpose() {
+        test_init();
+        let mut a = DeltaMatrix::new_lazy_transpose(unsafe { GrB_BOOL }, 10, 20);
+        assert!(a.transposed.get().is_none());
+        a.set_element_bool(1, 2);
+        a.wait(true);
+        a.set_element_bool(3, 4);
+
+        let t = a.transposed().unwrap();
+        assert_eq!((t.nrows(), t.ncols()), (20, 10));
+        assert_eq!(t.nvals(), 2);
+        assert_eq!(t.extract_element_bool(4, 3), Some(true));
+
+        // maintained once built
+        a.set_element_bool(5, 6);
+        a.remove_element(1, 2);
+        let t = a.transposed().unwrap();
+        assert_eq!(t.extract_element_bool(6, 5), Some(true));
+        assert_eq!(t.extract_element_bool(2, 1), None);
+        assert_eq!(t.nvals(), 2);
+
+        assert!(a.drop_transposed());
+        assert!(!a.drop_transposed());
+
+        // concurrent readers share a single build
+        let shared = SharedMatrix(&a);
+        let shared = &shared;
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(move || assert_eq!(shared.get().transposed().unwrap().nvals(), 2));
+            }
+        });
+
+        let stats = a.transpose_stats();
+        assert_eq!(
+            stats,
+            TransposeStats {
+                hits: 4,
+                builds: 2,
+                drops: 1
+            }
+        );
+        assert!((stats.hit_rate() - 4.0 / 6.0).abs() < f64::EPSILON);
+
+        let mut b = DeltaMatrix::new(unsafe { GrB_BOOL }, 10, 10, false);
+        assert!(b.transposed().is_none());
+        assert!(!b.drop_transposed());
+    }
+
+    #[test]
+    fn test_permute() {
+        test_init();
+        let mut a = DeltaMatrix::new(unsafe { GrB_UINT64 }, 5, 5, true);
+        a.set_element_u64(10, 0, 1);
+        a.set_element_u64(23, 2, 3);
+        a.wait(true);
+        a.set_element_u64(44, 4, 4);
+        a.set_element_u64(30, 3, 0);
+
+        a.permute(&[4, 2, 0], &[4, 3, 1]);
+        assert_eq!((a.nrows(), a.ncols()), (3, 3));
+        assert_eq!(a.nvals(), 3);
+        assert_eq!(a.extract_element_u64(0, 0), Some(44));
+        assert_eq!(a.extract_element_u64(1, 1), Some(23));
+        assert_eq!(a.extract_element_u64(2, 2), Some(10));
+
+        let t = a.transposed().unwrap();
+        assert_eq!((t.nrows(), t.ncols()), (3, 3));
+        assert_eq!(t.nvals(), 3);
+        assert_eq!(t.extract_element_bool(2, 2), Some(true));
+
+        a.map_values(|v| v + 1);
+        assert_eq!(a.extract_element_u64(0, 0), Some(45));
+        assert_eq!(a.extract_element_u64(2, 2), Some(11));
+        assert_eq!(a.nvals(), 3);
+    }
+
+    #[test]
+    fn test_async_free() {
+        test_init();
+        let _config = set_async_delete(true);
+
+        let mut handles = vec![];
+        for _ in 0..10 {
+            let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, 100, 100, true);
+            a.set_element_bool(1, 2);
+            a.wait(true);
+            // each matrix owns m, dp, dm and the same for its transposed
+            let since = reclaim::freed_count();
+            for m in [&a, a.transposed.get().map(Box::as_ref).unwrap()] {
+                handles
+                    .extend([m.m(), m.dp(), m.dm()].map(|m| (m.grb_matrix_ref() as usize, since)));
+            }
+            drop(a);
+        }
+        reclaim::flush();
+        for (m, since) in handles {
+            assert_eq!(reclaim::freed_on(m, since).as_deref(), Some("matrix-free"));
+        }
+    }
+
     #[test]
     fn test_resize() {
         test_init();
@@ -906,7 +1883,7 @@ mod tests {
         let ncols = 200;
 
         let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, nrows, ncols, true);
-        let t = a.transposed.as_ref().unwrap();
+        let t = a.transposed.get().unwrap();
 
         assert_eq!(a.nrows(), nrows);
         assert_eq!(a.matrix.nrows(), nrows);
@@ -933,7 +1910,7 @@ mod tests {
 
         a.resize(nrows, ncols);
 
-        let t = a.transposed.as_ref().unwrap();
+        let t = a.transposed.get().unwrap();
 
         assert_eq!(a.nrows(), nrows);
         assert_eq!(a.matrix.nrows(), nrows);
@@ -960,7 +1937,7 @@ mod tests {
 
         a.resize(nrows, ncols);
 
-        let t = a.transposed.as_ref().unwrap();
+        let t = a.transposed.get().unwrap();
 
         assert_eq!(a.nrows(), nrows);
         assert_eq!(a.matrix.nrows(), nrows);
@@ -982,4 +1959,67 @@ mod tests {
         assert_eq!(t.delta_plus.ncols(), nrows);
         assert_eq!(t.delta_minus.ncols(), nrows);
     }
+
+    #[test]
+    fn test_partition() {
+        test_init();
+        let nrows = 100;
+        let ncols = 100;
+
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, nrows, ncols, false);
+
+        assert_eq!(a.partition(4), vec![(0, nrows - 1)]);
+
+        for i in 0..nrows {
+            a.set_element_bool(i, i);
+        }
+        a.wait(true);
+        for i in 0..nrows {
+            a.set_element_bool(i, (i + 1) % ncols);
+        }
+
+        let ranges = a.partition(4);
+        assert_eq!(ranges.len(), 4);
+        assert_eq!(ranges[0].0, 0);
+        assert_eq!(ranges[3].1, nrows - 1);
+        for w in ranges.windows(2) {
+            assert_eq!(w[0].1 + 1, w[1].0);
+        }
+        for (min_row, max_row) in ranges {
+            assert_eq!(max_row - min_row + 1, nrows / 4);
+        }
+    }
+
+    #[test]
+    fn test_par_iter() {
+        test_init();
+        let nrows = 1000;
+        let ncols = 1000;
+
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, nrows, ncols, false);
+        for _ in 0..10000 {
+            let i = unsafe { rand() as u64 } % nrows;
+            let j = unsafe { rand() as u64 } % ncols;
+            a.set_element_bool(i, j);
+        }
+        for i in 0..nrows {
+            a.set_element_bool(i, i);
+        }
+        a.wait(true);
+        // leave pending deletions for the workers to face
+        for i in 0..nrows {
+            a.remove_element(i, i);
+        }
+        assert!(a.dm().pending());
+
+        let count = AtomicU64::new(0);
+        a.par_iter(8, |mut it| {
+            while let Ok(Some(_)) = it.next_bool() {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+        assert!(!a.m().pending() && !a.dp().pending() && !a.dm().pending());
+        assert_eq!(count.load(Ordering::Relaxed), a.nvals());
+    }
 }
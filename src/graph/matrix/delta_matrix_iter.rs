@@ -3,8 +3,18 @@
  * Licensed under the Server Side Public License v1 (SSPLv1).
  */
 
+use std::{
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
 use super::{
-    delta_matrix::DeltaMatrix, sparse_matrix_iter::SparseMatrixIter, GraphBLAS::GrB_Index,
+    delta_matrix::DeltaMatrix,
+    sparse_matrix_iter::{IterValue, SparseMatrixIter},
+    GraphBLAS::{GrB_Index, GrB_Info},
 };
 
 pub struct DeltaMatrixIter<'a> {
@@ -14,18 +24,54 @@ pub struct DeltaMatrixIter<'a> {
     dm_it: SparseMatrixIter,
     min_row: GrB_Index,
     max_row: GrB_Index,
+    version: u64,
+    // kept alive by the iterator so a freed matrix is detected without touching it
+    version_cell: Arc<AtomicU64>,
+    // row of the last returned entry
+    last_row: Option<u64>,
+    // rows skipped while iterating m, their dp entries are skipped too
+    skipped: BTreeSet<u64>,
 }
 
 impl<'a> DeltaMatrixIter<'a> {
     #[cfg(test)]
     pub fn new(m: &'a DeltaMatrix) -> DeltaMatrixIter<'a> {
+        Self::new_range(m, 0, u64::MAX)
+    }
+
+    /// Creates a new [`DeltaMatrixIter`] over the given row range of [`DeltaMatrix`].
+    pub fn new_range(
+        m: &'a DeltaMatrix,
+        min_row: u64,
+        max_row: u64,
+    ) -> DeltaMatrixIter<'a> {
         DeltaMatrixIter {
             matrix: Some(m),
+            min_row,
+            max_row,
+            version: m.version(),
+            version_cell: m.version_cell(),
+            last_row: None,
+            skipped: BTreeSet::new(),
+            m_it: SparseMatrixIter::new(m.m(), min_row, max_row),
+            dp_it: SparseMatrixIter::new(m.dp(), min_row, max_row),
+            dm_it: SparseMatrixIter::new(m.dm(), min_row, max_row),
+        }
+    }
+
+    /// Creates a [`DeltaMatrixIter`] attached to no matrix.
+    pub fn detached() -> DeltaMatrixIter<'a> {
+        DeltaMatrixIter {
+            matrix: None,
             min_row: 0,
             max_row: u64::MAX,
-            m_it: SparseMatrixIter::new(m.m(), 0, u64::MAX),
-            dp_it: SparseMatrixIter::new(m.dp(), 0, u64::MAX),
-            dm_it: SparseMatrixIter::new(m.dm(), 0, u64::MAX),
+            version: 0,
+            version_cell: Arc::new(AtomicU64::new(0)),
+            last_row: None,
+            skipped: BTreeSet::new(),
+            m_it: SparseMatrixIter::empty(),
+            dp_it: SparseMatrixIter::empty(),
+            dm_it: SparseMatrixIter::empty(),
         }
     }
 
@@ -47,6 +93,10 @@ impl<'a> DeltaMatrixIter<'a> {
         self.matrix = Some(m);
         self.min_row = min_row;
         self.max_row = max_row;
+        self.version = m.version();
+        self.version_cell = m.version_cell();
+        self.last_row = None;
+        self.skipped.clear();
         self.m_it = SparseMatrixIter::new(m.m(), min_row, max_row);
         self.dp_it = SparseMatrixIter::new(m.dp(), min_row, max_row);
         self.dm_it = SparseMatrixIter::new(m.dm(), min_row, max_row);
@@ -62,7 +112,7 @@ impl<'a> DeltaMatrixIter<'a> {
         &self,
         m: &DeltaMatrix,
     ) -> bool {
-        self.matrix.is_some() && std::ptr::eq(self.matrix.unwrap().m(), m.m())
+        self.matrix.is_some_and(|a| std::ptr::eq(a, m))
     }
 
     /// Constraint the iterator to iterate over specific row.
@@ -82,70 +132,162 @@ impl<'a> DeltaMatrixIter<'a> {
         self.attach_range(self.matrix.unwrap(), start_row_idx, end_row_idx);
     }
 
-    /// Returns the next bool of this [`DeltaMatrixIter`].
+    /// Returns an error if the iterator is not attached or its matrix was
+    /// resized, flushed, cleared or freed since it was attached.
+    /// The matrix itself is not accessed so a freed matrix is safe to detect.
+    fn check(&self) -> Result<(), GrB_Info> {
+        if self.matrix.is_none() {
+            return Err(GrB_Info::GrB_NULL_POINTER);
+        }
+        if self.version_cell.load(Ordering::Acquire) != self.version {
+            return Err(GrB_Info::GrB_INVALID_OBJECT);
+        }
+        Ok(())
+    }
+
+    /// Move the iterator to the first entry at or after (row, col).
     ///
     /// # Errors
     ///
-    /// This function will return an error if no matrix was attached.
-    pub fn next_bool(&mut self) -> Result<Option<(u64, u64)>, ()> {
-        if self.matrix.is_none() {
-            return Err(());
+    /// This function will return an error if no matrix was attached
+    /// or if the matrix was modified since the iterator was attached.
+    pub fn seek(
+        &mut self,
+        row: u64,
+        col: u64,
+    ) -> Result<(), GrB_Info> {
+        self.check()?;
+
+        let (row, col) = if row < self.min_row {
+            (self.min_row, 0)
+        } else {
+            (row, col)
+        };
+
+        self.m_it.seek(row, col, self.max_row);
+        self.dp_it.seek(row, col, self.max_row);
+        self.dm_it.seek(row, col, self.max_row);
+        self.last_row = None;
+        self.skipped.clear();
+
+        Ok(())
+    }
+
+    /// Skip the remaining entries of the current row, in m and in dp.
+    /// The current row is the row of the last returned entry,
+    /// or the row the iterator is at if nothing was returned since attach or seek.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if no matrix was attached
+    /// or if the matrix was modified since the iterator was attached.
+    pub fn skip_row(&mut self) -> Result<(), GrB_Info> {
+        self.check()?;
+
+        let row = match self.last_row.take() {
+            Some(row) => row,
+            None => match self.m_it.get_row().or_else(|| self.dp_it.get_row()) {
+                Some(row) => row,
+                None => return Ok(()),
+            },
+        };
+
+        if self.m_it.get_row().is_some_and(|r| r <= row) {
+            self.m_it.seek(row + 1, 0, self.max_row);
+        }
+        // dm entries are subset of m entries keep them aligned
+        if self.dm_it.get_row().is_some_and(|r| r <= row) {
+            self.dm_it.seek(row + 1, 0, self.max_row);
         }
+        // dp entries are returned after m so earlier rows may still be ahead
+        self.skipped.insert(row);
+
+        Ok(())
+    }
+
+    // move dp past the rows that were skipped
+    fn skip_dp_rows(&mut self) {
+        while let Some(row) = self.dp_it.get_row() {
+            if !self.skipped.contains(&row) {
+                break;
+            }
+            self.dp_it.skip_row(self.max_row);
+        }
+    }
+
+    /// Returns the next bool of this [`DeltaMatrixIter`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if no matrix was attached
+    /// or if the matrix was modified since the iterator was attached.
+    pub fn next_bool(&mut self) -> Result<Option<(u64, u64)>, GrB_Info> {
+        self.check()?;
 
         while let (Some(i), Some(j)) = (self.m_it.get_row(), self.m_it.get_col()) {
             self.m_it.next(self.max_row);
             if let (Some(mi), Some(mj)) = (self.dm_it.get_row(), self.dm_it.get_col()) {
                 if i < mi || (i == mi && j < mj) {
+                    self.last_row = Some(i);
                     return Ok(Some((i, j)));
                 }
                 debug_assert!(i == mi && j == mj);
                 self.dm_it.next(self.max_row);
             } else {
+                self.last_row = Some(i);
                 return Ok(Some((i, j)));
             }
         }
 
+        self.skip_dp_rows();
         if let (Some(i), Some(j)) = (self.dp_it.get_row(), self.dp_it.get_col()) {
             self.dp_it.next(self.max_row);
+            self.last_row = Some(i);
             return Ok(Some((i, j)));
         }
 
         Ok(None)
     }
 
-    /// Returns the next u64 of this [`DeltaMatrixIter`].
+    /// Returns the next value of this [`DeltaMatrixIter`].
     ///
     /// # Errors
     ///
-    /// This function will return an error if no matrix was attached.
-    pub fn next_u64(&mut self) -> Result<Option<(u64, u64, u64)>, ()> {
-        if self.matrix.is_none() {
-            return Err(());
+    /// This function will return an error if no matrix was attached,
+    /// if the matrix was modified since the iterator was attached
+    /// or if T does not match the type of the attached matrix.
+    pub fn next<T: IterValue>(&mut self) -> Result<Option<(u64, u64, T)>, GrB_Info> {
+        self.check()?;
+        if self.matrix.unwrap().m().ty() != T::grb_type() {
+            return Err(GrB_Info::GrB_DOMAIN_MISMATCH);
         }
 
         while let (Some(i), Some(j), Some(v)) = (
             self.m_it.get_row(),
             self.m_it.get_col(),
-            self.m_it.get_u64(),
+            // the matrix type was checked above
+            unsafe { self.m_it.get::<T>() },
         ) {
             self.m_it.next(self.max_row);
             if let (Some(mi), Some(mj)) = (self.dm_it.get_row(), self.dm_it.get_col()) {
                 if i < mi || (i == mi && j < mj) {
+                    self.last_row = Some(i);
                     return Ok(Some((i, j, v)));
                 }
                 debug_assert!(i == mi && j == mj);
                 self.dm_it.next(self.max_row);
             } else {
+                self.last_row = Some(i);
                 return Ok(Some((i, j, v)));
             }
         }
 
-        if let (Some(i), Some(j), Some(v)) = (
-            self.dp_it.get_row(),
-            self.dp_it.get_col(),
-            self.dp_it.get_u64(),
-        ) {
+        self.skip_dp_rows();
+        if let (Some(i), Some(j), Some(v)) = (self.dp_it.get_row(), self.dp_it.get_col(), unsafe {
+            self.dp_it.get::<T>()
+        }) {
             self.dp_it.next(self.max_row);
+            self.last_row = Some(i);
             return Ok(Some((i, j, v)));
         }
 
@@ -153,6 +295,7 @@ impl<'a> DeltaMatrixIter<'a> {
     }
 
     /// Reset this [`DeltaMatrixIter`] to start from the beggining.
+    /// The attached matrix must not have been freed.
     pub fn reset(&mut self) {
         self.attach_range(self.matrix.unwrap(), self.min_row, self.max_row);
     }
@@ -163,7 +306,7 @@ mod tests {
 
     use crate::graph::matrix::{
         delta_matrix::DeltaMatrix,
-        GraphBLAS::{GrB_BOOL, GrB_Mode, GrB_init},
+        GraphBLAS::{GrB_BOOL, GrB_FP64, GrB_INT32, GrB_Info, GrB_Mode, GrB_UINT64, GrB_init},
     };
 
     use super::DeltaMatrixIter;
@@ -188,6 +331,22 @@ mod tests {
         assert!(it.matrix.is_none());
     }
 
+    #[test]
+    fn test_detached() {
+        test_init();
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, 10, 10, false);
+        a.set_element_bool(1, 2);
+
+        let mut it = DeltaMatrixIter::detached();
+        assert!(!it.is_attached(&a));
+        assert_eq!(it.next_bool(), Err(GrB_Info::GrB_NULL_POINTER));
+        assert_eq!(it.skip_row(), Err(GrB_Info::GrB_NULL_POINTER));
+
+        it.attach(&a);
+        assert_eq!(it.next_bool(), Ok(Some((1u64, 2u64))));
+        assert_eq!(it.next_bool(), Ok(None));
+    }
+
     #[test]
     fn test_next() {
         test_init();
@@ -208,6 +367,234 @@ mod tests {
         assert_eq!(it.next_bool(), Ok(None));
 
         it.detach();
-        assert_eq!(it.next_bool(), Err(()));
+        assert_eq!(it.next_bool(), Err(GrB_Info::GrB_NULL_POINTER));
+    }
+
+    #[test]
+    fn test_seek() {
+        test_init();
+        let nrows = 100;
+        let ncols = 100;
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, nrows, ncols, false);
+
+        a.set_element_bool(1, 2);
+        a.set_element_bool(1, 5);
+        a.set_element_bool(3, 4);
+        a.wait(true);
+        a.remove_element(1, 5);
+        a.set_element_bool(2, 3);
+        a.set_element_bool(4, 1);
+
+        let mut it = DeltaMatrixIter::new(&a);
+
+        assert_eq!(it.seek(1, 3), Ok(()));
+        assert_eq!(it.next_bool(), Ok(Some((3u64, 4u64))));
+        assert_eq!(it.next_bool(), Ok(Some((2u64, 3u64))));
+        assert_eq!(it.next_bool(), Ok(Some((4u64, 1u64))));
+        assert_eq!(it.next_bool(), Ok(None));
+
+        assert_eq!(it.seek(4, 0), Ok(()));
+        assert_eq!(it.next_bool(), Ok(Some((4u64, 1u64))));
+        assert_eq!(it.next_bool(), Ok(None));
+
+        assert_eq!(it.seek(0, 0), Ok(()));
+        assert_eq!(it.next_bool(), Ok(Some((1u64, 2u64))));
+
+        it.detach();
+        assert_eq!(it.seek(0, 0), Err(GrB_Info::GrB_NULL_POINTER));
+    }
+
+    #[test]
+    fn test_skip_row() {
+        test_init();
+        let nrows = 100;
+        let ncols = 100;
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, nrows, ncols, false);
+
+        a.set_element_bool(1, 1);
+        a.set_element_bool(1, 2);
+        a.set_element_bool(1, 3);
+        a.set_element_bool(2, 2);
+        a.wait(true);
+        a.remove_element(2, 2);
+        a.set_element_bool(3, 1);
+        a.set_element_bool(3, 2);
+        a.set_element_bool(4, 4);
+
+        let mut it = DeltaMatrixIter::new(&a);
+
+        assert_eq!(it.next_bool(), Ok(Some((1u64, 1u64))));
+        assert_eq!(it.skip_row(), Ok(()));
+        assert_eq!(it.next_bool(), Ok(Some((3u64, 1u64))));
+        assert_eq!(it.skip_row(), Ok(()));
+        assert_eq!(it.next_bool(), Ok(Some((4u64, 4u64))));
+        assert_eq!(it.next_bool(), Ok(None));
+    }
+
+    #[test]
+    fn test_skip_row_last_entry() {
+        test_init();
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, 10, 10, false);
+
+        a.set_element_bool(1, 1);
+        a.set_element_bool(2, 1);
+        a.set_element_bool(2, 2);
+        a.wait(true);
+
+        // (1, 1) is the last entry of its row, row 2 must not be skipped
+        let mut it = DeltaMatrixIter::new(&a);
+        assert_eq!(it.next_bool(), Ok(Some((1u64, 1u64))));
+        assert_eq!(it.skip_row(), Ok(()));
+        assert_eq!(it.next_bool(), Ok(Some((2u64, 1u64))));
+        assert_eq!(it.skip_row(), Ok(()));
+        assert_eq!(it.next_bool(), Ok(None));
+
+        // nothing returned yet, the row the iterator is at is skipped
+        let mut it = DeltaMatrixIter::new(&a);
+        assert_eq!(it.skip_row(), Ok(()));
+        assert_eq!(it.next_bool(), Ok(Some((2u64, 1u64))));
+    }
+
+    #[test]
+    fn test_skip_row_dp() {
+        test_init();
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, 10, 10, false);
+
+        a.set_element_bool(1, 1);
+        a.set_element_bool(1, 2);
+        a.wait(true);
+        a.set_element_bool(1, 5);
+        a.set_element_bool(3, 3);
+        a.set_element_bool(3, 4);
+        a.set_element_bool(4, 1);
+
+        let mut it = DeltaMatrixIter::new(&a);
+        assert_eq!(it.next_bool(), Ok(Some((1u64, 1u64))));
+        // skips (1, 2) in m and (1, 5) in dp
+        assert_eq!(it.skip_row(), Ok(()));
+        assert_eq!(it.next_bool(), Ok(Some((3u64, 3u64))));
+        assert_eq!(it.skip_row(), Ok(()));
+        assert_eq!(it.next_bool(), Ok(Some((4u64, 1u64))));
+        assert_eq!(it.next_bool(), Ok(None));
+    }
+
+    #[test]
+    fn test_skip_row_seek_back() {
+        test_init();
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, 10, 10, false);
+
+        a.set_element_bool(1, 1);
+        a.wait(true);
+        a.set_element_bool(1, 5);
+        a.set_element_bool(2, 2);
+
+        let mut it = DeltaMatrixIter::new(&a);
+        assert_eq!(it.next_bool(), Ok(Some((1u64, 1u64))));
+        assert_eq!(it.skip_row(), Ok(()));
+        assert_eq!(it.next_bool(), Ok(Some((2u64, 2u64))));
+
+        // seeking back forgets the skipped rows
+        assert_eq!(it.seek(0, 0), Ok(()));
+        assert_eq!(it.next_bool(), Ok(Some((1u64, 1u64))));
+        assert_eq!(it.next_bool(), Ok(Some((1u64, 5u64))));
+        assert_eq!(it.next_bool(), Ok(Some((2u64, 2u64))));
+        assert_eq!(it.next_bool(), Ok(None));
+    }
+
+    #[test]
+    fn test_next_typed() {
+        test_init();
+        let nrows = 100;
+        let ncols = 100;
+        let mut a = DeltaMatrix::new(unsafe { GrB_UINT64 }, nrows, ncols, false);
+
+        a.set_element_u64(10, 1, 2);
+        a.set_element_u64(20, 2, 3);
+        a.wait(true);
+        a.remove_element(1, 2);
+        a.set_element_u64(30, 3, 4);
+
+        let mut it = DeltaMatrixIter::new(&a);
+
+        assert_eq!(it.next::<u64>(), Ok(Some((2u64, 3u64, 20u64))));
+        assert_eq!(it.next::<u64>(), Ok(Some((3u64, 4u64, 30u64))));
+        assert_eq!(it.next::<u64>(), Ok(None));
+    }
+
+    #[test]
+    fn test_next_other_types() {
+        test_init();
+
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, 10, 10, false);
+        a.set_element_bool(1, 2);
+        a.wait(true);
+        a.set_element_bool(3, 4);
+        let mut it = DeltaMatrixIter::new(&a);
+        assert_eq!(it.next::<u64>(), Err(GrB_Info::GrB_DOMAIN_MISMATCH));
+        assert_eq!(it.next::<bool>(), Ok(Some((1u64, 2u64, true))));
+        assert_eq!(it.next::<bool>(), Ok(Some((3u64, 4u64, true))));
+        assert_eq!(it.next::<bool>(), Ok(None));
+
+        let mut a = DeltaMatrix::new(unsafe { GrB_INT32 }, 10, 10, false);
+        a.set_element_u64(7, 1, 1);
+        a.wait(true);
+        a.set_element_u64(8, 2, 2);
+        let mut it = DeltaMatrixIter::new(&a);
+        assert_eq!(it.next::<u32>(), Err(GrB_Info::GrB_DOMAIN_MISMATCH));
+        assert_eq!(it.next::<i32>(), Ok(Some((1u64, 1u64, 7i32))));
+        assert_eq!(it.next::<i32>(), Ok(Some((2u64, 2u64, 8i32))));
+        assert_eq!(it.next::<i32>(), Ok(None));
+
+        let mut a = DeltaMatrix::new(unsafe { GrB_FP64 }, 10, 10, false);
+        a.set_element_u64(5, 0, 9);
+        let mut it = DeltaMatrixIter::new(&a);
+        assert_eq!(it.next::<f32>(), Err(GrB_Info::GrB_DOMAIN_MISMATCH));
+        assert_eq!(it.next::<f64>(), Ok(Some((0u64, 9u64, 5f64))));
+        assert_eq!(it.next::<f64>(), Ok(None));
+    }
+
+    #[test]
+    fn test_stale() {
+        test_init();
+        let nrows = 100;
+        let ncols = 100;
+        let mut a = DeltaMatrix::new(unsafe { GrB_BOOL }, nrows, ncols, false);
+
+        a.set_element_bool(1, 2);
+        a.set_element_bool(2, 3);
+        a.wait(true);
+
+        // simulate the C side modifying the matrix while an iterator is attached
+        let p = &mut a as *mut DeltaMatrix;
+        let mut it = DeltaMatrixIter::new(unsafe { &*p });
+
+        assert_eq!(it.next_bool(), Ok(Some((1u64, 2u64))));
+
+        unsafe { (*p).resize(nrows * 2, ncols * 2) };
+
+        assert_eq!(it.next_bool(), Err(GrB_Info::GrB_INVALID_OBJECT));
+        assert_eq!(it.seek(0, 0), Err(GrB_Info::GrB_INVALID_OBJECT));
+
+        it.reset();
+
+        assert_eq!(it.next_bool(), Ok(Some((1u64, 2u64))));
+        assert_eq!(it.next_bool(), Ok(Some((2u64, 3u64))));
+        assert_eq!(it.next_bool(), Ok(None));
+    }
+
+    #[test]
+    fn test_freed() {
+        test_init();
+        let a = Box::new(DeltaMatrix::new(unsafe { GrB_BOOL }, 100, 100, false));
+        let mut it = DeltaMatrixIter::new(unsafe { &*Box::into_raw(a) });
+
+        assert_eq!(it.next_bool(), Ok(None));
+
+        // simulate the C side freeing the matrix while an iterator is attached
+        let p = it.matrix.unwrap() as *const DeltaMatrix as *mut DeltaMatrix;
+        drop(unsafe { Box::from_raw(p) });
+
+        assert_eq!(it.next_bool(), Err(GrB_Info::GrB_INVALID_OBJECT));
+        assert_eq!(it.seek(0, 0), Err(GrB_Info::GrB_INVALID_OBJECT));
     }
 }
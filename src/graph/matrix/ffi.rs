@@ -3,17 +3,31 @@
  * Licensed under the Server Side Public License v1 (SSPLv1).
  */
 
-use std::ptr::null_mut;
+use std::{ffi::c_void, ptr::null_mut};
+
+use crate::guard::ffi_guard;
 
 use super::{
     delta_matrix::DeltaMatrix,
     delta_matrix_iter::DeltaMatrixIter,
-    GraphBLAS::{GrB_Index, GrB_Info, GrB_Matrix, GrB_Semiring, GrB_Type},
+    reclaim,
+    GraphBLAS::{GrB_Index, GrB_Info, GrB_Matrix, GrB_Semiring, GrB_Type, GxB_FC32_t, GxB_FC64_t},
 };
 
 type _Matrix = *mut DeltaMatrix;
 type _MatrixTupleIter = *mut DeltaMatrixIter<'static>;
 
+/// Context passed back to the C callback of [`Delta_Matrix_parIter`].
+struct IterCtx(*mut c_void);
+
+unsafe impl Sync for IterCtx {}
+
+impl IterCtx {
+    fn ptr(&self) -> *mut c_void {
+        self.0
+    }
+}
+
 #[no_mangle]
 unsafe extern "C" fn Delta_Matrix_new(
     a: *mut _Matrix,
@@ -22,21 +36,62 @@ unsafe extern "C" fn Delta_Matrix_new(
     ncols: GrB_Index,
     transpose: bool,
 ) -> GrB_Info {
-    *a = Box::into_raw(Box::new(DeltaMatrix::new(ty, nrows, ncols, transpose)));
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        *a = Box::into_raw(Box::new(DeltaMatrix::new(ty, nrows, ncols, transpose)));
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn Delta_Matrix_getTranspose(c: _Matrix) -> _Matrix {
-    match (*c).transposed() {
-        Some(m) => m.as_mut() as *mut DeltaMatrix,
-        None => std::ptr::null_mut(),
-    }
+    ffi_guard!(c, null_mut(), {
+        match (*c).transposed() {
+            Some(m) => std::ptr::from_ref(m).cast_mut(),
+            None => std::ptr::null_mut(),
+        }
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Delta_Matrix_newLazyTranspose(
+    a: *mut _Matrix,
+    ty: GrB_Type,
+    nrows: GrB_Index,
+    ncols: GrB_Index,
+) -> GrB_Info {
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        *a = Box::into_raw(Box::new(DeltaMatrix::new_lazy_transpose(ty, nrows, ncols)));
+        GrB_Info::GrB_SUCCESS
+    })
+}
+
+/// The caller must have exclusive access to c,
+/// pointers returned by Delta_Matrix_getTranspose are dangling after.
+#[no_mangle]
+unsafe extern "C" fn Delta_Matrix_dropTranspose(c: _Matrix) -> bool {
+    ffi_guard!(c, false, { (*c).drop_transposed() })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Delta_Matrix_transposeStats(
+    c: _Matrix,
+    hits: *mut u64,
+    builds: *mut u64,
+    drops: *mut u64,
+) {
+    ffi_guard!(c, (), {
+        let stats = (*c).transpose_stats();
+        *hits = stats.hits;
+        *builds = stats.builds;
+        *drops = stats.drops;
+    })
 }
 
+/// The returned matrix may be larger than Delta_Matrix_nrows x Delta_Matrix_ncols,
+/// the margin left by growth holds no entries.
 #[no_mangle]
 unsafe extern "C" fn Delta_Matrix_M(c: _Matrix) -> GrB_Matrix {
-    (&*c).m().grb_matrix_ref()
+    ffi_guard!(c, null_mut(), { (&*c).m().grb_matrix_ref() })
 }
 
 #[no_mangle]
@@ -44,8 +99,10 @@ unsafe extern "C" fn Delta_Matrix_nrows(
     nrows: *mut GrB_Index,
     c: _Matrix,
 ) -> GrB_Info {
-    *nrows = (&*c).nrows();
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        *nrows = (&*c).nrows();
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -53,8 +110,10 @@ unsafe extern "C" fn Delta_Matrix_ncols(
     ncols: *mut GrB_Index,
     c: _Matrix,
 ) -> GrB_Info {
-    *ncols = (&*c).ncols();
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        *ncols = (&*c).ncols();
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -62,8 +121,10 @@ unsafe extern "C" fn Delta_Matrix_nvals(
     nvals: *mut GrB_Index,
     c: _Matrix,
 ) -> GrB_Info {
-    *nvals = (&*c).nvals();
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        *nvals = (&*c).nvals();
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -72,8 +133,10 @@ unsafe extern "C" fn Delta_Matrix_resize(
     nrows_new: GrB_Index,
     ncols_new: GrB_Index,
 ) -> GrB_Info {
-    (*c).resize(nrows_new, ncols_new);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        (*c).resize(nrows_new, ncols_new);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -82,8 +145,10 @@ unsafe extern "C" fn Delta_Matrix_setElement_BOOL(
     i: GrB_Index,
     j: GrB_Index,
 ) -> GrB_Info {
-    (*c).set_element_bool(i, j);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        (*c).set_element_bool(i, j);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -93,8 +158,10 @@ unsafe extern "C" fn Delta_Matrix_setElement_UINT64(
     i: GrB_Index,
     j: GrB_Index,
 ) -> GrB_Info {
-    (*c).set_element_u64(x, i, j);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        (*c).set_element_u64(x, i, j);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -104,14 +171,16 @@ unsafe extern "C" fn Delta_Matrix_extractElement_BOOL(
     i: GrB_Index,
     j: GrB_Index,
 ) -> GrB_Info {
-    if let Some(v) = (&*c).extract_element_bool(i, j) {
-        if !x.is_null() {
-            *x = v;
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        if let Some(v) = (&*c).extract_element_bool(i, j) {
+            if !x.is_null() {
+                *x = v;
+            }
+            GrB_Info::GrB_SUCCESS
+        } else {
+            GrB_Info::GrB_NO_VALUE
         }
-        GrB_Info::GrB_SUCCESS
-    } else {
-        GrB_Info::GrB_NO_VALUE
-    }
+    })
 }
 
 #[no_mangle]
@@ -121,14 +190,16 @@ unsafe extern "C" fn Delta_Matrix_extractElement_UINT64(
     i: GrB_Index,
     j: GrB_Index,
 ) -> GrB_Info {
-    if let Some(v) = (&*c).extract_element_u64(i, j) {
-        if !x.is_null() {
-            *x = v;
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        if let Some(v) = (&*c).extract_element_u64(i, j) {
+            if !x.is_null() {
+                *x = v;
+            }
+            GrB_Info::GrB_SUCCESS
+        } else {
+            GrB_Info::GrB_NO_VALUE
         }
-        GrB_Info::GrB_SUCCESS
-    } else {
-        GrB_Info::GrB_NO_VALUE
-    }
+    })
 }
 
 #[no_mangle]
@@ -137,8 +208,10 @@ unsafe extern "C" fn Delta_Matrix_removeElement(
     i: GrB_Index,
     j: GrB_Index,
 ) -> GrB_Info {
-    (*c).remove_element(i, j);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        (*c).remove_element(i, j);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -146,10 +219,12 @@ unsafe extern "C" fn Delta_Matrix_removeElements(
     c: _Matrix,
     m: GrB_Matrix,
 ) -> GrB_Info {
-    let m = From::from(m);
-    (*c).remove_elements(&m);
-    m.grb_matrix();
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        let m = From::from(m);
+        (*c).remove_elements(&m);
+        m.grb_matrix();
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -159,8 +234,10 @@ unsafe extern "C" fn Delta_mxm(
     a: _Matrix,
     b: _Matrix,
 ) -> GrB_Info {
-    (*c).mxm(semiring, &*a, &*b);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        (*c).mxm(semiring, &*a, &*b);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -170,14 +247,18 @@ unsafe extern "C" fn Delta_eWiseAdd(
     a: _Matrix,
     b: _Matrix,
 ) -> GrB_Info {
-    (*c).element_wise_add(semiring, &*a, &*b);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        (*c).element_wise_add(semiring, &*a, &*b);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn Delta_Matrix_clear(c: _Matrix) -> GrB_Info {
-    (*c).clear();
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        (*c).clear();
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -185,8 +266,10 @@ unsafe extern "C" fn Delta_Matrix_copy(
     c: _Matrix,
     a: _Matrix,
 ) -> GrB_Info {
-    (*c).copy(&*a);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        (*c).copy(&*a);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -194,8 +277,10 @@ unsafe extern "C" fn Delta_Matrix_export(
     a: *mut GrB_Matrix,
     c: _Matrix,
 ) -> GrB_Info {
-    *a = (&*c).export().grb_matrix();
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        *a = (&*c).export().grb_matrix();
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -203,8 +288,10 @@ unsafe extern "C" fn Delta_Matrix_pending(
     c: _Matrix,
     pending: *mut bool,
 ) -> GrB_Info {
-    *pending = (&*c).pending();
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        *pending = (&*c).pending();
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -212,8 +299,10 @@ unsafe extern "C" fn Delta_Matrix_wait(
     c: _Matrix,
     force_sync: bool,
 ) -> GrB_Info {
-    (*c).wait(force_sync);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        (*c).wait(force_sync);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -222,13 +311,81 @@ unsafe extern "C" fn Delta_Matrix_synchronize(
     nrows: GrB_Index,
     ncols: GrB_Index,
 ) {
-    (*c).synchronize(nrows, ncols);
+    ffi_guard!(c, (), {
+        (*c).synchronize(nrows, ncols);
+    })
+}
+
+/// ranges must have room for 2 * n entries,
+/// the inclusive first and last row of each partition.
+#[no_mangle]
+unsafe extern "C" fn Delta_Matrix_partition(
+    c: _Matrix,
+    n: u64,
+    ranges: *mut GrB_Index,
+    count: *mut u64,
+) -> GrB_Info {
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        let partitions = (*c).partition(n);
+        for (i, (min_row, max_row)) in partitions.iter().enumerate() {
+            *ranges.add(i * 2) = *min_row;
+            *ranges.add(i * 2 + 1) = *max_row;
+        }
+        *count = partitions.len() as u64;
+        GrB_Info::GrB_SUCCESS
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Delta_Matrix_parIter(
+    c: _Matrix,
+    nthreads: u64,
+    cb: extern "C" fn(_MatrixTupleIter, *mut c_void),
+    ctx: *mut c_void,
+) -> GrB_Info {
+    ffi_guard!(c, GrB_Info::GrB_PANIC, {
+        let ctx = IterCtx(ctx);
+        (*c).par_iter(nthreads, |mut it| {
+            cb(&mut it as *mut _ as _MatrixTupleIter, ctx.ptr());
+        });
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn Delta_Matrix_free(c: *mut _Matrix) {
-    drop(Box::from_raw(c.read_unaligned()));
-    c.write_unaligned(null_mut());
+    ffi_guard!((), {
+        drop(Box::from_raw(c.read_unaligned()));
+        c.write_unaligned(null_mut());
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Delta_Matrix_FlushAsyncFree() {
+    ffi_guard!((), { reclaim::flush() })
+}
+
+/// Allocates an iterator attached to no matrix, free it with Delta_MatrixTupleIter_free.
+/// The iterator owns memory so C must not allocate or copy its storage,
+/// every Delta_MatrixTupleIter_* function expects an iterator from here
+/// or from the Delta_Matrix_parIter callback.
+#[no_mangle]
+unsafe extern "C" fn Delta_MatrixTupleIter_new(iter: *mut _MatrixTupleIter) -> GrB_Info {
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        *iter = Box::into_raw(Box::new(DeltaMatrixIter::detached()));
+        GrB_Info::GrB_SUCCESS
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Delta_MatrixTupleIter_free(iter: *mut _MatrixTupleIter) {
+    ffi_guard!((), {
+        let it = iter.read_unaligned();
+        if !it.is_null() {
+            drop(Box::from_raw(it));
+            iter.write_unaligned(null_mut());
+        }
+    })
 }
 
 #[no_mangle]
@@ -236,8 +393,10 @@ unsafe extern "C" fn Delta_MatrixTupleIter_attach(
     iter: _MatrixTupleIter,
     a: _Matrix,
 ) -> GrB_Info {
-    (*iter).attach(&*a);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        (*iter).attach(&*a);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -247,14 +406,18 @@ unsafe extern "C" fn Delta_MatrixTupleIter_AttachRange(
     min_row: GrB_Index,
     max_row: GrB_Index,
 ) -> GrB_Info {
-    (*iter).attach_range(&*a, min_row, max_row);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        (*iter).attach_range(&*a, min_row, max_row);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
 unsafe extern "C" fn Delta_MatrixTupleIter_detach(iter: _MatrixTupleIter) -> GrB_Info {
-    (*iter).detach();
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        (*iter).detach();
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -262,7 +425,7 @@ unsafe extern "C" fn Delta_MatrixTupleIter_is_attached(
     iter: _MatrixTupleIter,
     a: _Matrix,
 ) -> bool {
-    (&*iter).is_attached(&*a)
+    ffi_guard!(false, { (&*iter).is_attached(&*a) })
 }
 
 #[no_mangle]
@@ -270,8 +433,10 @@ unsafe extern "C" fn Delta_MatrixTupleIter_iterate_row(
     iter: _MatrixTupleIter,
     row_idx: GrB_Index,
 ) -> GrB_Info {
-    (*iter).iterate_row(row_idx);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        (*iter).iterate_row(row_idx);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
@@ -280,62 +445,111 @@ unsafe extern "C" fn Delta_MatrixTupleIter_iterate_range(
     start_row_idx: GrB_Index,
     end_row_idx: GrB_Index,
 ) -> GrB_Info {
-    (*iter).iterate_range(start_row_idx, end_row_idx);
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        (*iter).iterate_range(start_row_idx, end_row_idx);
+        GrB_Info::GrB_SUCCESS
+    })
 }
 
 #[no_mangle]
-unsafe extern "C" fn Delta_MatrixTupleIter_next_BOOL(
+unsafe extern "C" fn Delta_MatrixTupleIter_seek(
     iter: _MatrixTupleIter,
-    row: *mut GrB_Index,
-    col: *mut GrB_Index,
-    val: *mut bool,
+    row: GrB_Index,
+    col: GrB_Index,
 ) -> GrB_Info {
-    match (*iter).next_bool() {
-        Ok(Some((r, c))) => {
-            if !row.is_null() {
-                *row = r;
-            }
-            if !col.is_null() {
-                *col = c;
-            }
-            if !val.is_null() {
-                *val = true;
-            }
-            GrB_Info::GrB_SUCCESS
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        match (*iter).seek(row, col) {
+            Ok(()) => GrB_Info::GrB_SUCCESS,
+            Err(info) => info,
         }
-        Ok(None) => GrB_Info::GxB_EXHAUSTED,
-        _ => GrB_Info::GrB_NULL_POINTER,
-    }
+    })
 }
 
 #[no_mangle]
-unsafe extern "C" fn Delta_MatrixTupleIter_next_UINT64(
+unsafe extern "C" fn Delta_MatrixTupleIter_skipRow(iter: _MatrixTupleIter) -> GrB_Info {
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        match (*iter).skip_row() {
+            Ok(()) => GrB_Info::GrB_SUCCESS,
+            Err(info) => info,
+        }
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Delta_MatrixTupleIter_next_BOOL(
     iter: _MatrixTupleIter,
     row: *mut GrB_Index,
     col: *mut GrB_Index,
-    val: *mut u64,
+    val: *mut bool,
 ) -> GrB_Info {
-    match (*iter).next_u64() {
-        Ok(Some((r, c, v))) => {
-            if !row.is_null() {
-                *row = r;
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        match (*iter).next_bool() {
+            Ok(Some((r, c))) => {
+                if !row.is_null() {
+                    *row = r;
+                }
+                if !col.is_null() {
+                    *col = c;
+                }
+                if !val.is_null() {
+                    *val = true;
+                }
+                GrB_Info::GrB_SUCCESS
             }
-            if !col.is_null() {
-                *col = c;
-            }
-            if !val.is_null() {
-                *val = v;
-            }
-            GrB_Info::GrB_SUCCESS
+            Ok(None) => GrB_Info::GxB_EXHAUSTED,
+            Err(info) => info,
         }
-        Ok(None) => GrB_Info::GxB_EXHAUSTED,
-        _ => GrB_Info::GrB_NULL_POINTER,
-    }
+    })
+}
+
+macro_rules! delta_matrix_tuple_iter_next {
+    ($name:ident, $ty:ty) => {
+        #[no_mangle]
+        unsafe extern "C" fn $name(
+            iter: _MatrixTupleIter,
+            row: *mut GrB_Index,
+            col: *mut GrB_Index,
+            val: *mut $ty,
+        ) -> GrB_Info {
+            ffi_guard!(GrB_Info::GrB_PANIC, {
+                match (*iter).next::<$ty>() {
+                    Ok(Some((r, c, v))) => {
+                        if !row.is_null() {
+                            *row = r;
+                        }
+                        if !col.is_null() {
+                            *col = c;
+                        }
+                        if !val.is_null() {
+                            *val = v;
+                        }
+                        GrB_Info::GrB_SUCCESS
+                    }
+                    Ok(None) => GrB_Info::GxB_EXHAUSTED,
+                    Err(info) => info,
+                }
+            })
+        }
+    };
 }
 
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_INT8, i8);
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_INT16, i16);
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_INT32, i32);
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_INT64, i64);
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_UINT8, u8);
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_UINT16, u16);
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_UINT32, u32);
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_UINT64, u64);
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_FP32, f32);
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_FP64, f64);
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_FC32, GxB_FC32_t);
+delta_matrix_tuple_iter_next!(Delta_MatrixTupleIter_next_FC64, GxB_FC64_t);
+
 #[no_mangle]
 unsafe extern "C" fn Delta_MatrixTupleIter_reset(iter: _MatrixTupleIter) -> GrB_Info {
-    (*iter).reset();
-    GrB_Info::GrB_SUCCESS
+    ffi_guard!(GrB_Info::GrB_PANIC, {
+        (*iter).reset();
+        GrB_Info::GrB_SUCCESS
+    })
 }
@@ -3,9 +3,10 @@
  * Licensed under the Server Side Public License v1 (SSPLv1).
  */
 
-mod GraphBLAS;
+pub(crate) mod GraphBLAS;
 pub mod delta_matrix;
-mod delta_matrix_iter;
+pub(crate) mod delta_matrix_iter;
 mod ffi;
+mod reclaim;
 pub mod sparse_matrix;
 mod sparse_matrix_iter;
@@ -0,0 +1,136 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{
+    mem::MaybeUninit,
+    sync::{
+        mpsc::{sync_channel, Receiver, SyncSender},
+        OnceLock,
+    },
+    thread,
+};
+
+use crate::{
+    binding::graph::{ConfigOptionField, Config_Option_get},
+    grb_check,
+};
+
+use super::GraphBLAS::{GrB_Info, GrB_Matrix, GrB_Matrix_free};
+
+// max number of matrices waiting to be freed
+// once full, dropping a matrix blocks until the worker catches up
+const QUEUE_CAP: usize = 1024;
+
+struct Handle(GrB_Matrix);
+
+// the worker is the only user of a handed off matrix
+unsafe impl Send for Handle {}
+
+enum Job {
+    Free(Handle),
+    Flush(SyncSender<()>),
+}
+
+static QUEUE: OnceLock<Option<SyncSender<Job>>> = OnceLock::new();
+
+// the matrices freed so far with the name of the thread that freed them
+#[cfg(test)]
+static FREED: std::sync::Mutex<Vec<(usize, Option<String>)>> = std::sync::Mutex::new(Vec::new());
+
+fn queue() -> Option<&'static SyncSender<Job>> {
+    QUEUE
+        .get_or_init(|| {
+            let (tx, rx) = sync_channel(QUEUE_CAP);
+            thread::Builder::new()
+                .name("matrix-free".to_string())
+                .spawn(move || worker(rx))
+                .ok()
+                .map(|_| tx)
+        })
+        .as_ref()
+}
+
+fn worker(rx: Receiver<Job>) {
+    for job in rx {
+        match job {
+            Job::Free(mut m) => {
+                free_now(&mut m.0);
+            }
+            Job::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+fn free_now(m: &mut GrB_Matrix) {
+    #[cfg(test)]
+    FREED
+        .lock()
+        .unwrap()
+        .push((*m as usize, thread::current().name().map(str::to_string)));
+    unsafe {
+        grb_check!(GrB_Matrix_free(m));
+    }
+}
+
+fn async_delete() -> bool {
+    let mut async_delete = MaybeUninit::<bool>::uninit();
+    unsafe {
+        Config_Option_get(ConfigOptionField::ASYNC_DELETE, async_delete.as_mut_ptr())
+            && async_delete.assume_init()
+    }
+}
+
+/// Free a matrix dropped as a whole on the background thread when async delete is on,
+/// otherwise or if the thread is not available free it on the calling thread.
+/// Short lived temporaries are freed directly as handing them off costs more.
+pub fn free(mut m: GrB_Matrix) {
+    if m.is_null() {
+        return;
+    }
+    if async_delete() {
+        if let Some(q) = queue() {
+            match q.send(Job::Free(Handle(m))) {
+                Ok(()) => return,
+                Err(err) => {
+                    let Job::Free(h) = err.0 else { unreachable!() };
+                    m = h.0;
+                }
+            }
+        }
+    }
+    free_now(&mut m);
+}
+
+/// Wait until every matrix handed off so far is freed.
+pub fn flush() {
+    let Some(q) = QUEUE.get().and_then(|q| q.as_ref()) else {
+        return;
+    };
+    let (tx, rx) = sync_channel(1);
+    if q.send(Job::Flush(tx)).is_ok() {
+        let _ = rx.recv();
+    }
+}
+
+/// Number of matrices freed so far.
+#[cfg(test)]
+pub fn freed_count() -> usize {
+    FREED.lock().unwrap().len()
+}
+
+/// The name of the thread that freed the matrix after the first since frees.
+/// None if it was not freed here, addresses may be reused once freed.
+#[cfg(test)]
+pub fn freed_on(
+    m: usize,
+    since: usize,
+) -> Option<String> {
+    FREED.lock().unwrap()[since..]
+        .iter()
+        .find(|(freed, _)| *freed == m)
+        .and_then(|(_, name)| name.clone())
+}
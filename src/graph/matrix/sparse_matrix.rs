@@ -7,16 +7,16 @@ use std::{mem::MaybeUninit, ptr::null_mut};
 
 use crate::graph::matrix::GraphBLAS::{
     GrB_DESC_R, GrB_IDENTITY_BOOL, GrB_Matrix_apply, GrB_Matrix_assign_Scalar, GrB_Matrix_clear,
-    GrB_Matrix_eWiseAdd_Semiring, GrB_Matrix_free, GrB_Matrix_removeElement,
+    GrB_Matrix_dup, GrB_Matrix_eWiseAdd_Semiring, GrB_Matrix_extract, GrB_Matrix_removeElement,
     GrB_Matrix_setElement_BOOL, GrB_Matrix_setElement_UINT64, GrB_WaitMode, GrB_mxm,
-    GxB_ALWAYS_HYPER, GxB_Matrix_Option_set, GxB_Option_Field,
+    GxB_ALWAYS_HYPER, GxB_Matrix_Option_set, GxB_Matrix_type, GxB_Option_Field,
 };
 
 use super::GraphBLAS::{
     GrB_BinaryOp, GrB_Descriptor, GrB_Index, GrB_Info, GrB_Matrix, GrB_Matrix_assign,
-    GrB_Matrix_extractElement_BOOL, GrB_Matrix_extractElement_UINT64, GrB_Matrix_ncols,
-    GrB_Matrix_new, GrB_Matrix_nrows, GrB_Matrix_nvals, GrB_Matrix_resize, GrB_Matrix_wait,
-    GrB_Scalar, GrB_Semiring, GrB_Type, GrB_transpose, GxB_Matrix_Pending,
+    GrB_Matrix_extractElement_BOOL, GrB_Matrix_extractElement_UINT64, GrB_Matrix_free,
+    GrB_Matrix_ncols, GrB_Matrix_new, GrB_Matrix_nrows, GrB_Matrix_nvals, GrB_Matrix_resize,
+    GrB_Matrix_wait, GrB_Scalar, GrB_Semiring, GrB_Type, GrB_transpose, GxB_Matrix_Pending,
 };
 
 #[macro_export]
@@ -90,6 +90,14 @@ impl SparseMatrix {
         }
     }
 
+    pub fn ty(&self) -> GrB_Type {
+        unsafe {
+            let mut ty = MaybeUninit::uninit();
+            grb_check!(GxB_Matrix_type(ty.as_mut_ptr(), self.0));
+            ty.assume_init()
+        }
+    }
+
     pub fn nrows(&self) -> u64 {
         unsafe {
             let mut nrows: MaybeUninit<u64> = MaybeUninit::uninit();
@@ -124,6 +132,15 @@ impl SparseMatrix {
         }
     }
 
+    #[must_use]
+    pub fn dup(&self) -> Self {
+        unsafe {
+            let mut m = MaybeUninit::uninit();
+            grb_check!(GrB_Matrix_dup(m.as_mut_ptr(), self.0));
+            Self(m.assume_init())
+        }
+    }
+
     pub fn clear(&mut self) {
         unsafe {
             grb_check!(GrB_Matrix_clear(self.0));
@@ -228,6 +245,29 @@ impl SparseMatrix {
         }
     }
 
+    /// Set this matrix to a(rows, cols), its dimensions must match the lists.
+    #[cfg_attr(not(feature = "rust_graph"), allow(dead_code))]
+    pub fn extract(
+        &mut self,
+        a: &SparseMatrix,
+        rows: &[u64],
+        cols: &[u64],
+    ) {
+        unsafe {
+            grb_check!(GrB_Matrix_extract(
+                self.0,
+                null_mut(),
+                null_mut(),
+                a.0,
+                rows.as_ptr(),
+                rows.len() as u64,
+                cols.as_ptr(),
+                cols.len() as u64,
+                null_mut()
+            ));
+        }
+    }
+
     pub fn pending(&self) -> bool {
         unsafe {
             let mut pending: MaybeUninit<bool> = MaybeUninit::uninit();
@@ -10,12 +10,60 @@ use crate::grb_check;
 use super::{
     sparse_matrix::SparseMatrix,
     GraphBLAS::{
-        GB_Iterator_opaque, GrB_Info, GxB_Iterator_get_UINT64, GxB_rowIterator_attach,
-        GxB_rowIterator_getColIndex, GxB_rowIterator_getRowIndex, GxB_rowIterator_nextCol,
-        GxB_rowIterator_nextRow, GxB_rowIterator_seekRow,
+        GB_Iterator_opaque, GrB_BOOL, GrB_FP32, GrB_FP64, GrB_INT16, GrB_INT32, GrB_INT64,
+        GrB_INT8, GrB_Info, GrB_Type, GrB_UINT16, GrB_UINT32, GrB_UINT64, GrB_UINT8, GxB_FC32,
+        GxB_FC32_t, GxB_FC64, GxB_FC64_t, GxB_Iterator, GxB_Iterator_get_BOOL,
+        GxB_Iterator_get_FC32, GxB_Iterator_get_FC64, GxB_Iterator_get_FP32, GxB_Iterator_get_FP64,
+        GxB_Iterator_get_INT16, GxB_Iterator_get_INT32, GxB_Iterator_get_INT64,
+        GxB_Iterator_get_INT8, GxB_Iterator_get_UINT16, GxB_Iterator_get_UINT32,
+        GxB_Iterator_get_UINT64, GxB_Iterator_get_UINT8, GxB_rowIterator_attach,
+        GxB_rowIterator_getColIndex, GxB_rowIterator_getRowIndex, GxB_rowIterator_kount,
+        GxB_rowIterator_kseek, GxB_rowIterator_nextCol, GxB_rowIterator_nextRow,
+        GxB_rowIterator_seekRow,
     },
 };
 
+/// GraphBLAS built-in type that can be read from an iterator.
+pub trait IterValue {
+    /// Returns the GraphBLAS type of this value.
+    fn grb_type() -> GrB_Type;
+
+    /// Returns the value the iterator points to.
+    ///
+    /// # Safety
+    ///
+    /// The iterator must point to an entry of a matrix of this type.
+    unsafe fn get(it: GxB_Iterator) -> Self;
+}
+
+macro_rules! iter_value {
+    ($ty:ty, $grb_ty:ident, $get:ident) => {
+        impl IterValue for $ty {
+            fn grb_type() -> GrB_Type {
+                unsafe { $grb_ty }
+            }
+
+            unsafe fn get(it: GxB_Iterator) -> Self {
+                $get(it)
+            }
+        }
+    };
+}
+
+iter_value!(bool, GrB_BOOL, GxB_Iterator_get_BOOL);
+iter_value!(i8, GrB_INT8, GxB_Iterator_get_INT8);
+iter_value!(i16, GrB_INT16, GxB_Iterator_get_INT16);
+iter_value!(i32, GrB_INT32, GxB_Iterator_get_INT32);
+iter_value!(i64, GrB_INT64, GxB_Iterator_get_INT64);
+iter_value!(u8, GrB_UINT8, GxB_Iterator_get_UINT8);
+iter_value!(u16, GrB_UINT16, GxB_Iterator_get_UINT16);
+iter_value!(u32, GrB_UINT32, GxB_Iterator_get_UINT32);
+iter_value!(u64, GrB_UINT64, GxB_Iterator_get_UINT64);
+iter_value!(f32, GrB_FP32, GxB_Iterator_get_FP32);
+iter_value!(f64, GrB_FP64, GxB_Iterator_get_FP64);
+iter_value!(GxB_FC32_t, GxB_FC32, GxB_Iterator_get_FC32);
+iter_value!(GxB_FC64_t, GxB_FC64, GxB_Iterator_get_FC64);
+
 pub struct SparseMatrixIter {
     it: GB_Iterator_opaque,
     depleted: bool,
@@ -43,6 +91,41 @@ impl SparseMatrixIter {
         }
     }
 
+    /// Returns an iterator over no entries, used by iterators attached to no matrix.
+    pub fn empty() -> SparseMatrixIter {
+        SparseMatrixIter {
+            // a zeroed GraphBLAS iterator is never read as it is depleted
+            it: unsafe { MaybeUninit::zeroed().assume_init() },
+            depleted: true,
+        }
+    }
+
+    /// Returns the row index and number of entries of every non empty row of the [`SparseMatrix`].
+    /// Uses the row pointers (or the hyper list) so the cost is proportional to the number of rows.
+    pub fn row_nvals(m: &SparseMatrix) -> Vec<(u64, u64)> {
+        unsafe {
+            let mut it = MaybeUninit::uninit();
+            grb_check!(GxB_rowIterator_attach(
+                it.as_mut_ptr(),
+                m.grb_matrix_ref(),
+                null_mut()
+            ));
+            let mut it = it.assume_init();
+
+            let kount = GxB_rowIterator_kount(&mut it);
+            let mut res = Vec::new();
+            for k in 0..kount {
+                if GxB_rowIterator_kseek(&mut it, k) == GrB_Info::GrB_SUCCESS {
+                    res.push((
+                        GxB_rowIterator_getRowIndex(&mut it),
+                        (it.pend - it.pstart) as u64,
+                    ));
+                }
+            }
+            res
+        }
+    }
+
     pub fn set_range(
         &mut self,
         min_row: u64,
@@ -81,21 +164,77 @@ impl SparseMatrixIter {
                 return false;
             }
 
-            let mut info = GxB_rowIterator_nextCol(&mut self.it);
-            if info != GrB_Info::GrB_SUCCESS {
+            if GxB_rowIterator_nextCol(&mut self.it) != GrB_Info::GrB_SUCCESS {
+                self.next_row(max_row);
+            }
+
+            !self.depleted
+        }
+    }
+
+    /// Move the iterator to the first entry of the next non empty row.
+    fn next_row(
+        &mut self,
+        max_row: u64,
+    ) {
+        unsafe {
+            let mut info = GxB_rowIterator_nextRow(&mut self.it);
+
+            while info == GrB_Info::GrB_NO_VALUE
+                && GxB_rowIterator_getRowIndex(&mut self.it) < max_row
+            {
                 info = GxB_rowIterator_nextRow(&mut self.it);
+            }
+
+            self.depleted = info != GrB_Info::GrB_SUCCESS
+                || GxB_rowIterator_getRowIndex(&mut self.it) > max_row;
+        }
+    }
+
+    /// Move the iterator to the first entry at or after (row, col).
+    pub fn seek(
+        &mut self,
+        row: u64,
+        col: u64,
+        max_row: u64,
+    ) {
+        self.set_range(row, max_row);
+        if self.depleted {
+            return;
+        }
+
+        unsafe {
+            if GxB_rowIterator_getRowIndex(&mut self.it) != row {
+                return;
+            }
 
-                while info == GrB_Info::GrB_NO_VALUE
-                    && GxB_rowIterator_getRowIndex(&mut self.it) < max_row
-                {
-                    info = GxB_rowIterator_nextRow(&mut self.it);
+            // binary search the first column >= col in the row
+            let mut lo = self.it.p;
+            let mut hi = self.it.pend;
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                if (*self.it.Ai.add(mid as usize) as u64) < col {
+                    lo = mid + 1;
+                } else {
+                    hi = mid;
                 }
+            }
 
-                self.depleted = info != GrB_Info::GrB_SUCCESS
-                    || GxB_rowIterator_getRowIndex(&mut self.it) > max_row;
+            if lo < self.it.pend {
+                self.it.p = lo;
+            } else {
+                self.next_row(max_row);
             }
+        }
+    }
 
-            !self.depleted
+    /// Skip the remaining entries of the current row.
+    pub fn skip_row(
+        &mut self,
+        max_row: u64,
+    ) {
+        if !self.depleted {
+            self.next_row(max_row);
         }
     }
 
@@ -115,11 +254,16 @@ impl SparseMatrixIter {
         unsafe { Some(GxB_rowIterator_getColIndex(&mut self.it)) }
     }
 
-    pub fn get_u64(&mut self) -> Option<u64> {
+    /// Returns the value of the current entry.
+    ///
+    /// # Safety
+    ///
+    /// T must match the type of the iterated matrix.
+    pub unsafe fn get<T: IterValue>(&mut self) -> Option<T> {
         if self.depleted {
             return None;
         }
 
-        unsafe { Some(GxB_Iterator_get_UINT64(&mut self.it)) }
+        Some(T::get(&mut self.it))
     }
 }
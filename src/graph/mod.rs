@@ -3,4 +3,17 @@
  * Licensed under the Server Side Public License v1 (SSPLv1).
  */
 
+// the Rust attribute sets, datablocks and entity wrappers are only
+// partly used by the exports the enabled features switch on
+#[allow(dead_code)]
+pub mod attributes;
+#[allow(dead_code)]
+pub mod datablock;
+#[allow(dead_code)]
+pub mod entity;
 pub mod matrix;
+#[cfg(feature = "rust_graph")]
+mod store;
+
+#[cfg(feature = "rust_graph")]
+pub use store::Graph;
@@ -0,0 +1,341 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{ffi::c_void, ptr::null_mut, slice::from_raw_parts};
+
+use crate::{
+    binding::graph::{Edge, EntityID, LabelID, Node, NodeID, RelationID},
+    graph::{matrix::delta_matrix::DeltaMatrix, Graph},
+    guard::ffi_guard,
+};
+
+type _Graph = *mut Graph;
+type _Matrix = *mut DeltaMatrix;
+
+unsafe fn slice<'a, T>(
+    ptr: *const T,
+    len: usize,
+) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        from_raw_parts(ptr, len)
+    }
+}
+
+unsafe fn transposed(
+    m: &mut DeltaMatrix,
+    transposed: bool,
+) -> _Matrix {
+    if !transposed {
+        return m;
+    }
+    match m.transposed() {
+        Some(t) => std::ptr::from_ref(t).cast_mut(),
+        None => null_mut(),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_New(
+    node_cap: usize,
+    edge_cap: usize,
+) -> _Graph {
+    ffi_guard!(null_mut(), {
+        Box::into_raw(Box::new(Graph::new(node_cap as u64, edge_cap as u64)))
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_AddLabel(g: _Graph) -> LabelID {
+    ffi_guard!(g, -1, { (*g).add_label() })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_RemoveLabel(
+    g: _Graph,
+    label_id: LabelID,
+) -> bool {
+    ffi_guard!(g, false, { (*g).remove_label(label_id) })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_AddRelationType(g: _Graph) -> RelationID {
+    ffi_guard!(g, -1, { (*g).add_relation_type() })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_RemoveRelation(
+    g: _Graph,
+    relation_id: RelationID,
+) -> bool {
+    ffi_guard!(g, false, { (*g).remove_relation(relation_id) })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_CreateNode(
+    g: _Graph,
+    n: *mut Node,
+    labels: *const LabelID,
+    label_count: u32,
+) {
+    ffi_guard!(g, (), {
+        (*g).create_node(&mut *n, slice(labels, label_count as usize))
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_CreateNodeWithId(
+    g: _Graph,
+    id: NodeID,
+    n: *mut Node,
+    labels: *const LabelID,
+    label_count: u32,
+) -> bool {
+    ffi_guard!(g, false, {
+        (*g).create_node_with_id(id, &mut *n, slice(labels, label_count as usize))
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_CreateEdge(
+    g: _Graph,
+    src: NodeID,
+    dest: NodeID,
+    r: RelationID,
+    e: *mut Edge,
+) {
+    ffi_guard!(g, (), { (*g).create_edge(src, dest, r, &mut *e) })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_CreateEdgeWithId(
+    g: _Graph,
+    id: EntityID,
+    src: NodeID,
+    dest: NodeID,
+    r: RelationID,
+    e: *mut Edge,
+) -> bool {
+    ffi_guard!(g, false, {
+        (*g).create_edge_with_id(id, src, dest, r, &mut *e)
+    })
+}
+
+/// Write up to cap ids of the edges of type r connecting src to dest,
+/// returns the number of edges which may exceed cap.
+#[no_mangle]
+unsafe extern "C" fn Graph_EdgesBetween(
+    g: _Graph,
+    src: NodeID,
+    dest: NodeID,
+    r: RelationID,
+    edges: *mut EntityID,
+    cap: usize,
+) -> usize {
+    ffi_guard!(g, 0, {
+        let ids = (*g).edges_between(src, dest, r);
+        for (i, id) in ids.iter().take(cap).enumerate() {
+            edges.add(i).write(*id);
+        }
+        ids.len()
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_GetNode(
+    g: _Graph,
+    id: NodeID,
+    n: *mut Node,
+) -> bool {
+    ffi_guard!(g, false, { (*g).get_node(id, &mut *n) })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_GetEdge(
+    g: _Graph,
+    id: EntityID,
+    e: *mut Edge,
+) -> bool {
+    ffi_guard!(g, false, { (*g).get_edge(id, &mut *e) })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_GetNodeLabels(
+    g: _Graph,
+    n: *const Node,
+    labels: *mut LabelID,
+    label_count: u32,
+) -> u32 {
+    ffi_guard!(g, 0, {
+        let node_labels = (*g).node_labels((*n).id);
+        let count = node_labels.len().min(label_count as usize);
+        if count > 0 {
+            labels.copy_from_nonoverlapping(node_labels.as_ptr(), count);
+        }
+        count as u32
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_LabelNode(
+    g: _Graph,
+    id: NodeID,
+    lbls: *const LabelID,
+    lbl_count: u32,
+) {
+    ffi_guard!(g, (), {
+        (*g).label_node(id, slice(lbls, lbl_count as usize))
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_RemoveNodeLabels(
+    g: _Graph,
+    id: NodeID,
+    lbls: *const LabelID,
+    lbl_count: u32,
+) {
+    ffi_guard!(g, (), {
+        (*g).remove_node_labels(id, slice(lbls, lbl_count as usize))
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_DeleteNodes(
+    g: _Graph,
+    nodes: *const Node,
+    count: u64,
+) {
+    ffi_guard!(g, (), { (*g).delete_nodes(slice(nodes, count as usize)) })
+}
+
+/// Delete the nodes with all their edges, callback is invoked for each
+/// removed edge before its attributes are freed.
+/// Returns the number of removed edges.
+#[no_mangle]
+unsafe extern "C" fn Graph_DetachDeleteNodes(
+    g: _Graph,
+    nodes: *const Node,
+    count: u64,
+    callback: Option<unsafe extern "C" fn(*mut c_void, *mut Edge)>,
+    ctx: *mut c_void,
+) -> u64 {
+    ffi_guard!(g, 0, {
+        (*g).detach_delete_nodes(slice(nodes, count as usize), |e| {
+            if let Some(cb) = callback {
+                cb(ctx, e);
+            }
+        })
+        .len() as u64
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_DeleteEdges(
+    g: _Graph,
+    edges: *const Edge,
+    count: u64,
+) {
+    ffi_guard!(g, (), { (*g).delete_edges(slice(edges, count as usize)) })
+}
+
+/// Compact entity ids, callback is invoked for each node and edge whose id changed.
+#[no_mangle]
+unsafe extern "C" fn Graph_Defragment(
+    g: _Graph,
+    callback: Option<unsafe extern "C" fn(*mut c_void, bool, u64, u64)>,
+    ctx: *mut c_void,
+) {
+    ffi_guard!(g, (), {
+        let (nodes, edges) = (*g).defragment();
+        let Some(cb) = callback else {
+            return;
+        };
+        for (is_edge, map) in [(false, nodes), (true, edges)] {
+            for (old, new) in map.into_iter().enumerate() {
+                if new != u64::MAX && new != old as u64 {
+                    cb(ctx, is_edge, old as u64, new);
+                }
+            }
+        }
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_NodeCount(g: _Graph) -> u64 {
+    ffi_guard!(g, 0, { (*g).node_count() })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_EdgeCount(g: _Graph) -> u64 {
+    ffi_guard!(g, 0, { (*g).edge_count() })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_LabelTypeCount(g: _Graph) -> i32 {
+    ffi_guard!(g, 0, { (*g).label_count() as i32 })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_RelationTypeCount(g: _Graph) -> i32 {
+    ffi_guard!(g, 0, { (*g).relation_count() as i32 })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_GetAdjacencyMatrix(
+    g: _Graph,
+    transpose: bool,
+) -> _Matrix {
+    ffi_guard!(g, null_mut(), {
+        transposed((*g).adjacency_matrix(), transpose)
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_GetNodeLabelMatrix(g: _Graph) -> _Matrix {
+    ffi_guard!(g, null_mut(), { (*g).node_label_matrix() })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_GetLabelMatrix(
+    g: _Graph,
+    label: LabelID,
+) -> _Matrix {
+    ffi_guard!(g, null_mut(), { (*g).label_matrix(label) })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_GetRelationMatrix(
+    g: _Graph,
+    relation_idx: RelationID,
+    transpose: bool,
+) -> _Matrix {
+    ffi_guard!(g, null_mut(), {
+        transposed((*g).relation_matrix(relation_idx), transpose)
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_Synchronize(g: _Graph) {
+    ffi_guard!(g, (), { (*g).synchronize() })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_ApplyAllPending(
+    g: _Graph,
+    force_flush: bool,
+) {
+    ffi_guard!(g, (), { (*g).apply_all_pending(force_flush) })
+}
+
+#[no_mangle]
+unsafe extern "C" fn Graph_Free(g: _Graph) {
+    ffi_guard!((), {
+        if !g.is_null() {
+            drop(Box::from_raw(g));
+        }
+    })
+}
@@ -0,0 +1,864 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{
+    ptr::{null, null_mut},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    binding::graph::{
+        AttributeSet, AttributeSet_Free, Edge, EntityID, LabelID, Node, NodeID, RelationID,
+    },
+    graph::{
+        datablock::DataBlock,
+        matrix::{
+            delta_matrix::DeltaMatrix,
+            delta_matrix_iter::DeltaMatrixIter,
+            GraphBLAS::{GrB_BOOL, GrB_UINT64},
+        },
+    },
+    guard::Poison,
+};
+
+use super::multi_edge;
+
+// items per datablock block
+const BLOCK_CAP: u64 = 16384;
+
+struct EdgeRecord {
+    attributes: AttributeSet,
+    src_id: NodeID,
+    dest_id: NodeID,
+    relation_id: RelationID,
+}
+
+/// A property graph owning its matrices:
+/// - adjacency: src x dest, set when any edge connects the nodes
+/// - node labels: node x label
+/// - label: diagonal node x node per label
+/// - relation: src x dest edge id per relationship type,
+///   encoded as in the C graph when nodes are connected by several edges
+pub struct Graph {
+    adjacency: DeltaMatrix,
+    node_labels: DeltaMatrix,
+    labels: Vec<DeltaMatrix>,
+    relations: Vec<DeltaMatrix>,
+    nodes: DataBlock<AttributeSet>,
+    edges: DataBlock<EdgeRecord>,
+    // number of rows and columns of the node x node matrices
+    node_cap: u64,
+    poisoned: AtomicBool,
+}
+
+impl Poison for Graph {
+    fn poison(&self) {
+        self.poisoned.store(true, Ordering::Relaxed);
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Graph {
+    fn drop(&mut self) {
+        for (_, set) in self.nodes.iter_mut() {
+            unsafe { AttributeSet_Free(set) };
+        }
+        for (_, e) in self.edges.iter_mut() {
+            unsafe { AttributeSet_Free(&mut e.attributes) };
+        }
+        for m in self.relations.iter() {
+            let mut it = DeltaMatrixIter::new_range(m, 0, u64::MAX);
+            while let Ok(Some((_, _, entry))) = it.next::<u64>() {
+                multi_edge::free(entry);
+            }
+        }
+    }
+}
+
+impl Graph {
+    pub fn new(
+        node_cap: u64,
+        edge_cap: u64,
+    ) -> Self {
+        let node_cap = node_cap.max(1);
+        unsafe {
+            Self {
+                adjacency: DeltaMatrix::new(GrB_BOOL, node_cap, node_cap, true),
+                node_labels: DeltaMatrix::new(GrB_BOOL, node_cap, 0, false),
+                labels: Vec::new(),
+                relations: Vec::new(),
+                nodes: DataBlock::new(BLOCK_CAP, node_cap),
+                edges: DataBlock::new(BLOCK_CAP, edge_cap),
+                node_cap,
+                poisoned: AtomicBool::new(false),
+            }
+        }
+    }
+
+    pub fn node_count(&self) -> u64 {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> u64 {
+        self.edges.len()
+    }
+
+    pub fn label_count(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn relation_count(&self) -> usize {
+        self.relations.len()
+    }
+
+    pub fn add_label(&mut self) -> LabelID {
+        let m = unsafe { DeltaMatrix::new(GrB_BOOL, self.node_cap, self.node_cap, false) };
+        self.labels.push(m);
+        self.node_labels
+            .resize(self.node_cap, self.labels.len() as u64);
+        self.labels.len() as LabelID - 1
+    }
+
+    /// Remove the last label, returns false if label_id is not the last
+    /// label or nodes still have it.
+    pub fn remove_label(
+        &mut self,
+        label_id: LabelID,
+    ) -> bool {
+        if label_id < 0
+            || label_id as usize + 1 != self.labels.len()
+            || self.labels[label_id as usize].nvals() > 0
+        {
+            return false;
+        }
+        self.labels.pop();
+        self.node_labels
+            .resize(self.node_cap, self.labels.len() as u64);
+        true
+    }
+
+    pub fn add_relation_type(&mut self) -> RelationID {
+        let m = unsafe { DeltaMatrix::new(GrB_UINT64, self.node_cap, self.node_cap, true) };
+        self.relations.push(m);
+        self.relations.len() as RelationID - 1
+    }
+
+    /// Remove the last relationship type, returns false if relation_id is not
+    /// the last relationship type or edges still have it.
+    pub fn remove_relation(
+        &mut self,
+        relation_id: RelationID,
+    ) -> bool {
+        if relation_id < 0
+            || relation_id as usize + 1 != self.relations.len()
+            || self.relations[relation_id as usize].nvals() > 0
+        {
+            return false;
+        }
+        self.relations.pop();
+        true
+    }
+
+    // grow the node x node matrices to fit id
+    fn reserve_node(
+        &mut self,
+        id: NodeID,
+    ) {
+        let id = id as u64;
+        if id < self.node_cap {
+            return;
+        }
+        self.node_cap = (self.node_cap * 2).max(id + 1);
+        let cap = self.node_cap;
+        self.adjacency.resize(cap, cap);
+        self.node_labels.resize(cap, self.labels.len() as u64);
+        for m in self.labels.iter_mut().chain(self.relations.iter_mut()) {
+            m.resize(cap, cap);
+        }
+    }
+
+    fn node_ref(
+        &mut self,
+        id: NodeID,
+        n: &mut Node,
+    ) -> bool {
+        let Some(set) = self.nodes.get_mut(id as u64) else {
+            return false;
+        };
+        n.id = id;
+        n.attributes = set;
+        true
+    }
+
+    pub fn create_node(
+        &mut self,
+        n: &mut Node,
+        labels: &[LabelID],
+    ) {
+        let id = self.nodes.insert(null_mut()) as NodeID;
+        self.reserve_node(id);
+        self.label_node(id, labels);
+        self.node_ref(id, n);
+    }
+
+    /// Create a node at a specific id, used to undo a deletion.
+    /// Returns false if the id is in use.
+    pub fn create_node_with_id(
+        &mut self,
+        id: NodeID,
+        n: &mut Node,
+        labels: &[LabelID],
+    ) -> bool {
+        if !self.nodes.insert_at(id as u64, null_mut()) {
+            return false;
+        }
+        self.reserve_node(id);
+        self.label_node(id, labels);
+        self.node_ref(id, n)
+    }
+
+    pub fn get_node(
+        &mut self,
+        id: NodeID,
+        n: &mut Node,
+    ) -> bool {
+        self.node_ref(id, n)
+    }
+
+    pub fn node_labels(
+        &self,
+        id: NodeID,
+    ) -> Vec<LabelID> {
+        let mut it = DeltaMatrixIter::new_range(&self.node_labels, id as u64, id as u64);
+        let mut labels = vec![];
+        while let Ok(Some((_, l))) = it.next_bool() {
+            labels.push(l as LabelID);
+        }
+        labels
+    }
+
+    pub fn label_node(
+        &mut self,
+        id: NodeID,
+        labels: &[LabelID],
+    ) {
+        for l in labels {
+            self.node_labels.set_element_bool(id as u64, *l as u64);
+            self.labels[*l as usize].set_element_bool(id as u64, id as u64);
+        }
+    }
+
+    pub fn remove_node_labels(
+        &mut self,
+        id: NodeID,
+        labels: &[LabelID],
+    ) {
+        for l in labels {
+            self.node_labels.remove_element(id as u64, *l as u64);
+            self.labels[*l as usize].remove_element(id as u64, id as u64);
+        }
+    }
+
+    /// Nodes must be detached, their edges deleted first.
+    pub fn delete_nodes(
+        &mut self,
+        nodes: &[Node],
+    ) {
+        for n in nodes {
+            let labels = self.node_labels(n.id);
+            self.remove_node_labels(n.id, &labels);
+            if let Some(mut set) = self.nodes.remove(n.id as u64) {
+                unsafe { AttributeSet_Free(&mut set) };
+            }
+        }
+    }
+
+    /// Delete the nodes together with every incident edge by clearing their
+    /// rows and columns, proportional to the nodes degree.
+    /// deleted is called with each removed edge before its attributes are freed.
+    /// Returns the ids of the removed edges.
+    pub fn detach_delete_nodes(
+        &mut self,
+        nodes: &[Node],
+        mut deleted: impl FnMut(&mut Edge),
+    ) -> Vec<EntityID> {
+        let mut edges = vec![];
+        for n in nodes {
+            let id = n.id as u64;
+            for r in 0..self.relations.len() {
+                let m = &mut self.relations[r];
+                let outgoing = m.remove_row(id).into_iter().map(|(dest, e)| (id, dest, e));
+                let incoming = m.remove_col(id).into_iter().map(|(src, e)| (src, id, e));
+                for (_, _, entry) in outgoing.chain(incoming).collect::<Vec<_>>() {
+                    edges.extend(multi_edge::ids(entry));
+                    multi_edge::free(entry);
+                }
+            }
+            self.adjacency.remove_row(id);
+            self.adjacency.remove_col(id);
+
+            for (l, _) in self.node_labels.remove_row(id) {
+                self.labels[l as usize].remove_element(id, id);
+            }
+            if let Some(mut set) = self.nodes.remove(id) {
+                unsafe { AttributeSet_Free(&mut set) };
+            }
+        }
+
+        for id in edges.iter() {
+            let Some(mut r) = self.edges.remove(*id as u64) else {
+                continue;
+            };
+            deleted(&mut Edge {
+                attributes: &mut r.attributes,
+                id: *id,
+                relationship: null(),
+                relation_id: r.relation_id,
+                src_id: r.src_id,
+                dest_id: r.dest_id,
+            });
+            unsafe { AttributeSet_Free(&mut r.attributes) };
+        }
+        edges
+    }
+
+    fn edge_ref(
+        &mut self,
+        id: EntityID,
+        e: &mut Edge,
+    ) -> bool {
+        let Some(r) = self.edges.get_mut(id as u64) else {
+            return false;
+        };
+        e.id = id;
+        e.attributes = &mut r.attributes;
+        e.src_id = r.src_id;
+        e.dest_id = r.dest_id;
+        e.relation_id = r.relation_id;
+        true
+    }
+
+    fn connect(
+        &mut self,
+        id: EntityID,
+        src: NodeID,
+        dest: NodeID,
+        r: RelationID,
+    ) {
+        let m = &mut self.relations[r as usize];
+        match m.extract_element_u64(src as u64, dest as u64) {
+            None => m.set_element_u64(id as u64, src as u64, dest as u64),
+            Some(entry) if multi_edge::is_single(entry) => {
+                let entry = multi_edge::new(&[entry as EntityID, id]);
+                m.set_element_u64(entry, src as u64, dest as u64);
+            }
+            Some(entry) => multi_edge::add(entry, id),
+        }
+        self.adjacency.set_element_bool(src as u64, dest as u64);
+    }
+
+    fn disconnect(
+        &mut self,
+        id: EntityID,
+        src: NodeID,
+        dest: NodeID,
+        r: RelationID,
+    ) {
+        let m = &mut self.relations[r as usize];
+        if let Some(entry) = m.extract_element_u64(src as u64, dest as u64) {
+            if !multi_edge::is_single(entry) {
+                let remaining = multi_edge::remove(entry, id);
+                if remaining != entry {
+                    m.set_element_u64(remaining, src as u64, dest as u64);
+                }
+                return;
+            }
+        }
+
+        m.remove_element(src as u64, dest as u64);
+        let connected = self
+            .relations
+            .iter()
+            .any(|m| m.extract_element_u64(src as u64, dest as u64).is_some());
+        if !connected {
+            self.adjacency.remove_element(src as u64, dest as u64);
+        }
+    }
+
+    pub fn create_edge(
+        &mut self,
+        src: NodeID,
+        dest: NodeID,
+        r: RelationID,
+        e: &mut Edge,
+    ) {
+        let id = self.edges.insert(EdgeRecord {
+            attributes: null_mut(),
+            src_id: src,
+            dest_id: dest,
+            relation_id: r,
+        }) as EntityID;
+        self.connect(id, src, dest, r);
+        self.edge_ref(id, e);
+    }
+
+    /// Create an edge at a specific id, used to undo a deletion.
+    /// Returns false if the id is in use.
+    pub fn create_edge_with_id(
+        &mut self,
+        id: EntityID,
+        src: NodeID,
+        dest: NodeID,
+        r: RelationID,
+        e: &mut Edge,
+    ) -> bool {
+        let inserted = self.edges.insert_at(
+            id as u64,
+            EdgeRecord {
+                attributes: null_mut(),
+                src_id: src,
+                dest_id: dest,
+                relation_id: r,
+            },
+        );
+        if !inserted {
+            return false;
+        }
+        self.connect(id, src, dest, r);
+        self.edge_ref(id, e)
+    }
+
+    pub fn get_edge(
+        &mut self,
+        id: EntityID,
+        e: &mut Edge,
+    ) -> bool {
+        self.edge_ref(id, e)
+    }
+
+    /// Returns the ids of the edges of type r connecting src to dest.
+    pub fn edges_between(
+        &self,
+        src: NodeID,
+        dest: NodeID,
+        r: RelationID,
+    ) -> Vec<EntityID> {
+        self.relations
+            .get(r as usize)
+            .and_then(|m| m.extract_element_u64(src as u64, dest as u64))
+            .map_or(vec![], multi_edge::ids)
+    }
+
+    pub fn delete_edges(
+        &mut self,
+        edges: &[Edge],
+    ) {
+        for e in edges {
+            let Some(mut r) = self.edges.remove(e.id as u64) else {
+                continue;
+            };
+            self.disconnect(e.id, r.src_id, r.dest_id, r.relation_id);
+            unsafe { AttributeSet_Free(&mut r.attributes) };
+        }
+    }
+
+    pub fn adjacency_matrix(&mut self) -> &mut DeltaMatrix {
+        let cap = self.node_cap;
+        self.adjacency.synchronize(cap, cap);
+        &mut self.adjacency
+    }
+
+    pub fn node_label_matrix(&mut self) -> &mut DeltaMatrix {
+        let (cap, labels) = (self.node_cap, self.labels.len() as u64);
+        self.node_labels.synchronize(cap, labels);
+        &mut self.node_labels
+    }
+
+    pub fn label_matrix(
+        &mut self,
+        label_id: LabelID,
+    ) -> &mut DeltaMatrix {
+        let cap = self.node_cap;
+        let m = &mut self.labels[label_id as usize];
+        m.synchronize(cap, cap);
+        m
+    }
+
+    pub fn relation_matrix(
+        &mut self,
+        relation_id: RelationID,
+    ) -> &mut DeltaMatrix {
+        let cap = self.node_cap;
+        let m = &mut self.relations[relation_id as usize];
+        m.synchronize(cap, cap);
+        m
+    }
+
+    /// Compact node and edge ids left sparse by deletions and shrink every
+    /// matrix to the node count, entity addresses handed out before are invalidated.
+    /// Returns the new id of every old node and edge id, deleted ids map to u64::MAX.
+    pub fn defragment(&mut self) -> (Vec<u64>, Vec<u64>) {
+        let nodes = self.nodes.compact();
+        let edges = self.edges.compact();
+
+        // new node id i is the i-th live old id
+        let keep = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, new)| **new != u64::MAX)
+            .map(|(old, _)| old as u64)
+            .collect::<Vec<_>>();
+        let labels = (0..self.labels.len() as u64).collect::<Vec<_>>();
+
+        for (_, e) in self.edges.iter_mut() {
+            e.src_id = nodes[e.src_id as usize] as NodeID;
+            e.dest_id = nodes[e.dest_id as usize] as NodeID;
+        }
+        self.adjacency.permute(&keep, &keep);
+        self.node_labels.permute(&keep, &labels);
+        for m in self.labels.iter_mut() {
+            m.permute(&keep, &keep);
+        }
+        for m in self.relations.iter_mut() {
+            m.permute(&keep, &keep);
+            m.map_values(|entry| {
+                if multi_edge::is_single(entry) {
+                    return edges[entry as usize];
+                }
+                let ids = multi_edge::ids(entry)
+                    .into_iter()
+                    .map(|id| edges[id as usize] as EntityID)
+                    .collect::<Vec<_>>();
+                multi_edge::free(entry);
+                multi_edge::new(&ids)
+            });
+        }
+
+        self.node_cap = (keep.len() as u64).max(1);
+        self.synchronize();
+        (nodes, edges)
+    }
+
+    /// Bring every matrix to the graph dimensions and apply its pending changes.
+    pub fn synchronize(&mut self) {
+        let (cap, labels) = (self.node_cap, self.labels.len() as u64);
+        self.adjacency.synchronize(cap, cap);
+        self.node_labels.synchronize(cap, labels);
+        for m in self.labels.iter_mut().chain(self.relations.iter_mut()) {
+            m.synchronize(cap, cap);
+        }
+    }
+
+    pub fn apply_all_pending(
+        &mut self,
+        force_flush: bool,
+    ) {
+        self.adjacency.wait(force_flush);
+        self.node_labels.wait(force_flush);
+        for m in self.labels.iter_mut().chain(self.relations.iter_mut()) {
+            m.wait(force_flush);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::{null, null_mut};
+
+    use crate::{
+        binding::graph::{ConfigOptionField, Config_Option_set, Edge, EntityID, Node, NodeID},
+        graph::matrix::GraphBLAS::{
+            GrB_Mode, GrB_init, GxB_Format_Value, GxB_Global_Option_set, GxB_Option_Field,
+        },
+    };
+
+    use super::{multi_edge, Graph};
+
+    fn test_init() {
+        unsafe {
+            GrB_init(GrB_Mode::GrB_NONBLOCKING);
+            GxB_Global_Option_set(GxB_Option_Field::GxB_FORMAT, GxB_Format_Value::GxB_BY_ROW);
+            Config_Option_set(
+                ConfigOptionField::DELTA_MAX_PENDING_CHANGES,
+                c"10000".as_ptr(),
+                null_mut(),
+            );
+        };
+    }
+
+    fn node(
+        g: &mut Graph,
+        labels: &[i32],
+    ) -> Node {
+        let mut n = Node {
+            attributes: null_mut(),
+            id: -1,
+        };
+        g.create_node(&mut n, labels);
+        n
+    }
+
+    fn edge(
+        g: &mut Graph,
+        src: NodeID,
+        dest: NodeID,
+        r: i32,
+    ) -> Edge {
+        let mut e = Edge {
+            attributes: null_mut(),
+            id: -1,
+            relationship: null(),
+            relation_id: -1,
+            src_id: -1,
+            dest_id: -1,
+        };
+        g.create_edge(src, dest, r, &mut e);
+        e
+    }
+
+    fn connected(
+        g: &mut Graph,
+        src: NodeID,
+        dest: NodeID,
+    ) -> bool {
+        g.adjacency_matrix()
+            .extract_element_bool(src as u64, dest as u64)
+            .is_some()
+    }
+
+    #[test]
+    fn test_create_delete() {
+        test_init();
+        let mut g = Graph::new(2, 2);
+        let r = g.add_relation_type();
+        let nodes = (0..4).map(|_| node(&mut g, &[])).collect::<Vec<_>>();
+        assert_eq!(
+            nodes.iter().map(|n| n.id).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(g.node_count(), 4);
+
+        let e = edge(&mut g, 0, 3, r);
+        assert_eq!(g.edge_count(), 1);
+        assert!(connected(&mut g, 0, 3));
+        let mut fetched = e;
+        assert!(g.get_edge(e.id, &mut fetched));
+        assert_eq!((fetched.src_id, fetched.dest_id), (0, 3));
+
+        g.delete_edges(&[e]);
+        assert_eq!(g.edge_count(), 0);
+        assert!(!g.get_edge(e.id, &mut fetched));
+        assert!(!connected(&mut g, 0, 3));
+
+        g.delete_nodes(&nodes[1..3]);
+        assert_eq!(g.node_count(), 2);
+        let mut n = nodes[0];
+        assert!(!g.get_node(1, &mut n));
+        assert!(g.get_node(3, &mut n));
+
+        // deleted ids are reused
+        assert_eq!(node(&mut g, &[]).id, 2);
+
+        // an id in use is not created again
+        let mut restored = nodes[1];
+        assert!(g.create_node_with_id(1, &mut restored, &[]));
+        assert!(!g.create_node_with_id(1, &mut restored, &[]));
+        assert_eq!(g.node_count(), 4);
+        let e = edge(&mut g, 0, 1, r);
+        let mut dup = e;
+        assert!(!g.create_edge_with_id(e.id, 1, 0, r, &mut dup));
+        assert_eq!(g.edge_count(), 1);
+        assert!(!connected(&mut g, 1, 0));
+    }
+
+    #[test]
+    fn test_labels() {
+        test_init();
+        let mut g = Graph::new(16, 16);
+        let a = g.add_label();
+        let b = g.add_label();
+        let n = node(&mut g, &[a]);
+        assert_eq!(g.node_labels(n.id), vec![a]);
+
+        g.label_node(n.id, &[b]);
+        assert_eq!(g.node_labels(n.id), vec![a, b]);
+        assert_eq!(g.label_matrix(b).nvals(), 1);
+
+        // only the last label without nodes can be removed
+        assert!(!g.remove_label(a));
+        assert!(!g.remove_label(b));
+        g.remove_node_labels(n.id, &[b]);
+        assert_eq!(g.node_labels(n.id), vec![a]);
+        assert!(g.remove_label(b));
+        assert_eq!(g.label_count(), 1);
+    }
+
+    #[test]
+    fn test_multi_edges() {
+        test_init();
+        let mut g = Graph::new(16, 16);
+        let r = g.add_relation_type();
+        let src = node(&mut g, &[]).id;
+        let dest = node(&mut g, &[]).id;
+        let edges = (0..3)
+            .map(|_| edge(&mut g, src, dest, r))
+            .collect::<Vec<_>>();
+        let ids = edges.iter().map(|e| e.id).collect::<Vec<EntityID>>();
+        assert_eq!(g.edges_between(src, dest, r), ids);
+        assert_eq!(g.relation_matrix(r).nvals(), 1);
+
+        // the relation matrix entry decodes as in the C graph
+        g.delete_edges(&edges[..1]);
+        assert_eq!(g.edges_between(src, dest, r), ids[1..]);
+        let entry = g
+            .relation_matrix(r)
+            .extract_element_u64(src as u64, dest as u64)
+            .unwrap();
+        assert!(!multi_edge::is_single(entry));
+        assert_eq!(multi_edge::ids(entry), ids[1..]);
+
+        // a single remaining edge is stored as its id
+        g.delete_edges(&edges[1..2]);
+        assert_eq!(g.edges_between(src, dest, r), ids[2..]);
+        assert_eq!(
+            g.relation_matrix(r)
+                .extract_element_u64(src as u64, dest as u64),
+            Some(ids[2] as u64)
+        );
+        assert!(connected(&mut g, src, dest));
+
+        // a relationship type with edges can not be removed
+        assert!(!g.remove_relation(r));
+        g.delete_edges(&edges[2..]);
+        assert!(g.edges_between(src, dest, r).is_empty());
+        assert!(!connected(&mut g, src, dest));
+        assert!(g.remove_relation(r));
+        assert_eq!(g.relation_count(), 0);
+    }
+
+    #[test]
+    fn test_detach_delete() {
+        test_init();
+        let mut g = Graph::new(16, 16);
+        let label = g.add_label();
+        let r = g.add_relation_type();
+        let s = g.add_relation_type();
+        let hub = node(&mut g, &[label]).id;
+        let x = node(&mut g, &[label]).id;
+        let y = node(&mut g, &[]).id;
+
+        let mut hub_edges = vec![
+            edge(&mut g, hub, hub, r).id,
+            edge(&mut g, hub, x, r).id,
+            edge(&mut g, hub, x, r).id,
+            edge(&mut g, x, hub, r).id,
+            edge(&mut g, hub, y, s).id,
+        ];
+        let kept = edge(&mut g, y, x, r).id;
+        g.synchronize();
+
+        let mut reported = vec![];
+        let mut removed = g.detach_delete_nodes(
+            &[Node {
+                attributes: null_mut(),
+                id: hub,
+            }],
+            |e| reported.push((e.id, e.src_id, e.dest_id)),
+        );
+
+        // every edge is removed once, self loops included
+        removed.sort_unstable();
+        hub_edges.sort_unstable();
+        assert_eq!(removed, hub_edges);
+        assert_eq!(reported.len(), hub_edges.len());
+        assert!(reported
+            .iter()
+            .all(|(_, src, dest)| *src == hub || *dest == hub));
+
+        assert_eq!(g.node_count(), 2);
+        assert_eq!(g.edge_count(), 1);
+        assert_eq!(g.label_matrix(label).nvals(), 1);
+        assert!(g.node_labels(hub).is_empty());
+        assert_eq!(g.node_labels(x), vec![label]);
+        assert!(g.edges_between(hub, x, r).is_empty());
+        assert!(g.edges_between(hub, y, s).is_empty());
+        assert_eq!(g.edges_between(y, x, r), vec![kept]);
+        assert_eq!(g.relation_matrix(s).nvals(), 0);
+        assert_eq!(g.adjacency_matrix().nvals(), 1);
+        assert!(connected(&mut g, y, x));
+    }
+
+    #[test]
+    fn test_defragment() {
+        test_init();
+        let mut g = Graph::new(4, 4);
+        let label = g.add_label();
+        let r = g.add_relation_type();
+        let labeled = [label];
+        let nodes = (0..6)
+            .map(|i| node(&mut g, if i % 3 == 1 { &labeled } else { &[] }).id)
+            .collect::<Vec<_>>();
+
+        // a hole in the edge ids and a multi-edge
+        let hole = edge(&mut g, nodes[1], nodes[5], r);
+        let edges = [
+            edge(&mut g, nodes[1], nodes[2], r),
+            edge(&mut g, nodes[4], nodes[5], r),
+            edge(&mut g, nodes[4], nodes[5], r),
+            edge(&mut g, nodes[2], nodes[2], r),
+        ];
+        g.delete_edges(&[hole]);
+        // holes in the node ids
+        let deleted = [nodes[0], nodes[3]];
+        g.delete_nodes(&deleted.map(|id| Node {
+            attributes: null_mut(),
+            id,
+        }));
+
+        let (node_map, edge_map) = g.defragment();
+        assert_eq!(node_map, vec![u64::MAX, 0, 1, u64::MAX, 2, 3]);
+        assert_eq!(edge_map[hole.id as usize], u64::MAX);
+        assert_eq!(g.node_count(), 4);
+        assert_eq!(g.edge_count(), 4);
+
+        for e in edges.iter() {
+            let id = edge_map[e.id as usize] as EntityID;
+            let src = node_map[e.src_id as usize] as NodeID;
+            let dest = node_map[e.dest_id as usize] as NodeID;
+            let mut moved = *e;
+            assert!(g.get_edge(id, &mut moved));
+            assert_eq!(
+                (moved.src_id, moved.dest_id, moved.relation_id),
+                (src, dest, r)
+            );
+
+            // the relation matrix entry holds the moved ids
+            let between = g.edges_between(src, dest, r);
+            assert!(between.contains(&id));
+            let entry = g
+                .relation_matrix(r)
+                .extract_element_u64(src as u64, dest as u64)
+                .unwrap();
+            assert_eq!(multi_edge::ids(entry), between);
+            assert!(connected(&mut g, src, dest));
+        }
+        let multi = [edges[1].id, edges[2].id].map(|id| edge_map[id as usize] as EntityID);
+        assert_eq!(g.edges_between(2, 3, r), multi);
+
+        assert_eq!(g.node_labels(0), vec![label]);
+        assert_eq!(g.node_labels(2), vec![label]);
+        assert!(g.node_labels(1).is_empty());
+        let m = g.label_matrix(label);
+        assert_eq!((m.nrows(), m.nvals()), (4, 2));
+        assert_eq!(m.extract_element_bool(2, 2), Some(true));
+        let m = g.relation_matrix(r);
+        assert_eq!((m.nrows(), m.ncols(), m.nvals()), (4, 4, 3));
+        assert_eq!(g.adjacency_matrix().nvals(), 3);
+        assert_eq!(g.node_label_matrix().nvals(), 2);
+    }
+}
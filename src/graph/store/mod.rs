@@ -0,0 +1,10 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+mod ffi;
+mod graph;
+mod multi_edge;
+
+pub use graph::Graph;
@@ -0,0 +1,107 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+// Relation matrix entries use the encoding of the C graph:
+// a single edge is stored as its id, nodes connected by several edges
+// of the same type store a BOOL GrB_Vector of the edge ids with the MSB set.
+
+use std::{mem::MaybeUninit, ptr::null_mut};
+
+use crate::{
+    binding::graph::EntityID,
+    graph::matrix::GraphBLAS::{
+        GrB_BOOL, GrB_Info, GrB_Vector, GrB_Vector_extractTuples_BOOL, GrB_Vector_free,
+        GrB_Vector_new, GrB_Vector_nvals, GrB_Vector_removeElement, GrB_Vector_setElement_BOOL,
+    },
+    grb_check,
+};
+
+const MSB_MASK: u64 = 1 << 63;
+
+// GrB_INDEX_MAX, any edge id fits in the vector
+const INDEX_MAX: u64 = (1 << 60) - 1;
+
+pub(super) fn is_single(entry: u64) -> bool {
+    entry & MSB_MASK == 0
+}
+
+fn vector(entry: u64) -> GrB_Vector {
+    debug_assert!(!is_single(entry));
+    (entry & !MSB_MASK) as GrB_Vector
+}
+
+/// Returns a multi-edge entry holding ids.
+pub(super) fn new(ids: &[EntityID]) -> u64 {
+    unsafe {
+        let mut v = MaybeUninit::uninit();
+        grb_check!(GrB_Vector_new(v.as_mut_ptr(), GrB_BOOL, INDEX_MAX));
+        let v = v.assume_init();
+        for id in ids {
+            grb_check!(GrB_Vector_setElement_BOOL(v, true, *id as u64));
+        }
+        v as u64 | MSB_MASK
+    }
+}
+
+/// Add id to a multi-edge entry.
+pub(super) fn add(
+    entry: u64,
+    id: EntityID,
+) {
+    unsafe {
+        grb_check!(GrB_Vector_setElement_BOOL(vector(entry), true, id as u64));
+    }
+}
+
+/// Remove id from a multi-edge entry and returns the entry to store,
+/// once a single edge remains the vector is freed and its id is returned.
+pub(super) fn remove(
+    entry: u64,
+    id: EntityID,
+) -> u64 {
+    unsafe {
+        grb_check!(GrB_Vector_removeElement(vector(entry), id as u64));
+    }
+    match ids(entry)[..] {
+        [remaining] => {
+            free(entry);
+            remaining as u64
+        }
+        _ => entry,
+    }
+}
+
+/// Returns the edge ids of a single or multi-edge entry.
+pub(super) fn ids(entry: u64) -> Vec<EntityID> {
+    if is_single(entry) {
+        return vec![entry as EntityID];
+    }
+    unsafe {
+        let v = vector(entry);
+        let mut nvals = MaybeUninit::uninit();
+        grb_check!(GrB_Vector_nvals(nvals.as_mut_ptr(), v));
+        let mut nvals = nvals.assume_init();
+        let mut ids = vec![0u64; nvals as usize];
+        grb_check!(GrB_Vector_extractTuples_BOOL(
+            ids.as_mut_ptr(),
+            null_mut(),
+            &mut nvals,
+            v
+        ));
+        ids.truncate(nvals as usize);
+        ids.into_iter().map(|id| id as EntityID).collect()
+    }
+}
+
+/// Free the vector of a multi-edge entry, single edge entries own nothing.
+pub(super) fn free(entry: u64) {
+    if is_single(entry) {
+        return;
+    }
+    let mut v = vector(entry);
+    unsafe {
+        grb_check!(GrB_Vector_free(&mut v));
+    }
+}
@@ -0,0 +1,66 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::any::Any;
+
+/// An object owned by C that may be left inconsistent by a panic.
+/// Once poisoned every export refuses to touch it.
+pub trait Poison {
+    fn poison(&self);
+    fn is_poisoned(&self) -> bool;
+}
+
+pub fn report_panic(
+    name: &str,
+    payload: Box<dyn Any + Send>,
+) {
+    let msg = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    // in case the load time install did not run
+    crate::logger::install();
+    log::error!("{name} panicked: {msg}");
+}
+
+/// Run the body of an extern "C" function without unwinding into C.
+/// On panic the message is reported and default is returned.
+/// Given an object, the object is poisoned on panic and calls on
+/// a poisoned object return default without running the body.
+macro_rules! ffi_guard {
+    (@name) => {{
+        fn f() {}
+        let name = std::any::type_name_of_val(&f);
+        let name = name.strip_suffix("::f").unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name)
+    }};
+    ($default:expr, $body:block) => {{
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| $body)) {
+            Ok(res) => res,
+            Err(payload) => {
+                $crate::guard::report_panic(ffi_guard!(@name), payload);
+                $default
+            }
+        }
+    }};
+    ($obj:expr, $default:expr, $body:block) => {{
+        let obj = $obj;
+        if $crate::guard::Poison::is_poisoned(&*obj) {
+            $default
+        } else {
+            match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| $body)) {
+                Ok(res) => res,
+                Err(payload) => {
+                    $crate::guard::report_panic(ffi_guard!(@name), payload);
+                    $crate::guard::Poison::poison(&*obj);
+                    $default
+                }
+            }
+        }
+    }};
+}
+
+pub(crate) use ffi_guard;
@@ -5,9 +5,14 @@
 
 use std::alloc::{GlobalAlloc, Layout};
 use std::os::raw::c_void;
+use std::ptr::null_mut;
 
 mod binding;
+mod cdc;
+mod effects;
 mod graph;
+mod guard;
+mod logger;
 mod undo_log;
 
 extern "C" {
@@ -37,7 +42,14 @@ unsafe impl GlobalAlloc for FalkorDBAlloc {
 
         match RedisModule_Alloc {
             Some(alloc) => alloc(size).cast(),
-            None => panic!("alloc function not found"),
+            // unwinding out of the allocator is undefined behaviour
+            // a null pointer reports the failure to the caller,
+            // the message is written directly as logging would allocate
+            None => {
+                const MSG: &[u8] = b"FalkorDB: RedisModule_Alloc is not set, allocation failed\n";
+                libc::write(libc::STDERR_FILENO, MSG.as_ptr().cast(), MSG.len());
+                null_mut()
+            }
         }
     }
 
@@ -46,10 +58,9 @@ unsafe impl GlobalAlloc for FalkorDBAlloc {
         ptr: *mut u8,
         _layout: Layout,
     ) {
-        match RedisModule_Free {
-            Some(f) => f(ptr.cast()),
-            None => panic!("free function not found"),
-        };
+        if let Some(f) = RedisModule_Free {
+            f(ptr.cast());
+        }
     }
 }
 
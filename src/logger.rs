@@ -0,0 +1,190 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::{
+    ffi::{c_char, c_void, CString},
+    io::{self, Write},
+    sync::Once,
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+extern "C" {
+    static RedisModule_Log:
+        Option<unsafe extern "C" fn(*mut c_void, *const c_char, *const c_char, ...)>;
+}
+
+/// Forward log records to RedisModule_Log, stderr when not loaded by Redis.
+struct RedisLogger;
+
+static LOGGER: RedisLogger = RedisLogger;
+static INIT: Once = Once::new();
+
+// level until Logging_SetLevel is called
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::Warn;
+
+// install the logger when the module is loaded so panics reported
+// before Logging_SetLevel are not dropped
+#[used]
+#[cfg_attr(target_os = "linux", link_section = ".init_array")]
+#[cfg_attr(target_os = "macos", link_section = "__DATA,__mod_init_func")]
+static INSTALL_AT_LOAD: extern "C" fn() = {
+    extern "C" fn install_at_load() {
+        install();
+    }
+    install_at_load
+};
+
+fn redis_level(level: Level) -> &'static [u8] {
+    match level {
+        Level::Error | Level::Warn => b"warning\0",
+        Level::Info => b"notice\0",
+        Level::Debug => b"verbose\0",
+        Level::Trace => b"debug\0",
+    }
+}
+
+impl Log for RedisLogger {
+    fn enabled(
+        &self,
+        metadata: &Metadata,
+    ) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(
+        &self,
+        record: &Record,
+    ) {
+        self.log_to(record, &mut io::stderr());
+    }
+
+    fn flush(&self) {}
+}
+
+impl RedisLogger {
+    // log to Redis or to fallback when not loaded by Redis
+    fn log_to(
+        &self,
+        record: &Record,
+        fallback: &mut impl Write,
+    ) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let msg = format!("{}: {}", record.target(), record.args());
+        match unsafe { RedisModule_Log } {
+            Some(f) => {
+                let Ok(msg) = CString::new(msg) else {
+                    return;
+                };
+                unsafe {
+                    f(
+                        std::ptr::null_mut(),
+                        redis_level(record.level()).as_ptr().cast(),
+                        c"%s".as_ptr(),
+                        msg.as_ptr(),
+                    )
+                };
+            }
+            None => {
+                let _ = writeln!(fallback, "[{}] {msg}", record.level());
+            }
+        }
+    }
+}
+
+/// Install the logger at the default level if it is not installed yet.
+pub fn install() {
+    INIT.call_once(|| {
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(DEFAULT_LEVEL);
+        }
+    });
+}
+
+/// Install the logger and set its level.
+pub fn init(level: LevelFilter) {
+    install();
+    log::set_max_level(level);
+}
+
+// levels as numbered by the log crate, 0 turns logging off
+fn level_filter(level: u32) -> LevelFilter {
+    match level {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn Logging_SetLevel(level: u32) {
+    init(level_filter(level));
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{Level, LevelFilter, Record};
+
+    use super::{init, install, level_filter, redis_level, RedisLogger};
+
+    #[test]
+    fn test_levels() {
+        let filters = [
+            LevelFilter::Off,
+            LevelFilter::Error,
+            LevelFilter::Warn,
+            LevelFilter::Info,
+            LevelFilter::Debug,
+            LevelFilter::Trace,
+        ];
+        for (level, filter) in filters.iter().enumerate() {
+            assert_eq!(level_filter(level as u32), *filter);
+        }
+        assert_eq!(level_filter(100), LevelFilter::Trace);
+
+        assert_eq!(redis_level(Level::Error), b"warning\0");
+        assert_eq!(redis_level(Level::Warn), b"warning\0");
+        assert_eq!(redis_level(Level::Info), b"notice\0");
+        assert_eq!(redis_level(Level::Debug), b"verbose\0");
+        assert_eq!(redis_level(Level::Trace), b"debug\0");
+    }
+
+    #[test]
+    fn test_stderr_fallback() {
+        // installed when loaded at the default level
+        install();
+        assert!(log::max_level() >= LevelFilter::Error);
+        init(LevelFilter::Warn);
+
+        let mut out = vec![];
+        let logger = RedisLogger;
+        logger.log_to(
+            &Record::builder()
+                .level(Level::Error)
+                .target("falkordb")
+                .args(format_args!("boom"))
+                .build(),
+            &mut out,
+        );
+        assert_eq!(String::from_utf8(out).unwrap(), "[ERROR] falkordb: boom\n");
+
+        // records above the level are dropped
+        let mut out = vec![];
+        logger.log_to(
+            &Record::builder()
+                .level(Level::Trace)
+                .args(format_args!("quiet"))
+                .build(),
+            &mut out,
+        );
+        assert!(out.is_empty());
+    }
+}
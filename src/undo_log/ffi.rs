@@ -3,17 +3,39 @@
  * Licensed under the Server Side Public License v1 (SSPLv1).
  */
 
-use std::{ffi::c_char, slice::from_raw_parts};
+use std::{
+    ffi::{c_char, CStr},
+    ptr::null_mut,
+    slice::from_raw_parts,
+};
 
-use crate::binding::graph::*;
+use crate::{
+    binding::graph::*,
+    guard::{ffi_guard, Poison},
+};
 
 use super::undo_log::*;
 
 type _UndoLog = *mut UndoLog;
 
+// move the attribute set of a deleted entity into the log
+// the C attribute set is tagged with the high pointer bit so deleting the entity keeps it
+#[cfg(not(feature = "rust_attribute_set"))]
+unsafe fn take_attributes(attributes: *mut AttributeSet) -> AttributeSet {
+    let set = attributes.read_unaligned();
+    attributes.write((set as u64 | (1u64 << (u64::BITS as usize - 1))) as *mut _);
+    set
+}
+
+// the rust attribute set is moved out leaving the entity with an empty set
+#[cfg(feature = "rust_attribute_set")]
+unsafe fn take_attributes(attributes: *mut AttributeSet) -> AttributeSet {
+    std::mem::replace(&mut *attributes, std::ptr::null_mut())
+}
+
 #[no_mangle]
 unsafe extern "C" fn UndoLog_New() -> _UndoLog {
-    Box::into_raw(Box::new(UndoLog::new()))
+    ffi_guard!(null_mut(), { Box::into_raw(Box::new(UndoLog::new())) })
 }
 
 #[no_mangle]
@@ -21,7 +43,9 @@ unsafe extern "C" fn UndoLog_CreateNode(
     log: _UndoLog,
     node: *const Node,
 ) {
-    (*log).create_node(node.read());
+    ffi_guard!(log, (), {
+        (*log).create_node(node.read());
+    })
 }
 
 #[no_mangle]
@@ -29,7 +53,9 @@ unsafe extern "C" fn UndoLog_CreateEdge(
     log: _UndoLog,
     edge: *const Edge,
 ) {
-    (*log).create_edge(edge.read());
+    ffi_guard!(log, (), {
+        (*log).create_edge(edge.read());
+    })
 }
 
 #[no_mangle]
@@ -39,11 +65,16 @@ unsafe extern "C" fn UndoLog_DeleteNode(
     labels: *const LabelID,
     labels_count: usize,
 ) {
-    let n = node.read();
-    let set = n.attributes.read_unaligned();
-    n.attributes
-        .write((set as u64 | (1u64 << (u64::BITS as usize - 1))) as *mut _);
-    (*log).delete_node(n.id, set, from_raw_parts(labels, labels_count).to_vec());
+    ffi_guard!(log, (), {
+        let n = node.read();
+        let set = take_attributes(n.attributes);
+        let labels = if labels_count == 0 {
+            &[]
+        } else {
+            from_raw_parts(labels, labels_count)
+        };
+        (*log).delete_node(n.id, set, labels);
+    })
 }
 
 #[no_mangle]
@@ -51,11 +82,11 @@ unsafe extern "C" fn UndoLog_DeleteEdge(
     log: _UndoLog,
     edge: *const Edge,
 ) {
-    let e = edge.read();
-    let set = e.attributes.read_unaligned();
-    e.attributes
-        .write((set as u64 | (1u64 << (u64::BITS as usize - 1))) as *mut _);
-    (*log).delete_edge(e.id, e.src_id, e.dest_id, e.relation_id, set);
+    ffi_guard!(log, (), {
+        let e = edge.read();
+        let set = take_attributes(e.attributes);
+        (*log).delete_edge(e.id, e.src_id, e.dest_id, e.relation_id, set);
+    })
 }
 
 #[no_mangle]
@@ -64,7 +95,9 @@ unsafe extern "C" fn UndoLog_UpdateNode(
     node: *const Node,
     old_set: AttributeSet,
 ) {
-    (*log).update_node(node.read(), old_set);
+    ffi_guard!(log, (), {
+        (*log).update_node(node.read(), old_set);
+    })
 }
 
 #[no_mangle]
@@ -73,7 +106,33 @@ unsafe extern "C" fn UndoLog_UpdateEdge(
     edge: *const Edge,
     old_set: AttributeSet,
 ) {
-    (*log).update_edge(edge.read(), old_set);
+    ffi_guard!(log, (), {
+        (*log).update_edge(edge.read(), old_set);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_UpdateNodeAttr(
+    log: _UndoLog,
+    node: *const Node,
+    attr_id: AttributeID,
+    old_value: RawSIValue,
+) {
+    ffi_guard!(log, (), {
+        (*log).update_node_attr((*node).id, attr_id, old_value);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_UpdateEdgeAttr(
+    log: _UndoLog,
+    edge: *const Edge,
+    attr_id: AttributeID,
+    old_value: RawSIValue,
+) {
+    ffi_guard!(log, (), {
+        (*log).update_edge_attr(edge.read(), attr_id, old_value);
+    })
 }
 
 #[no_mangle]
@@ -83,10 +142,12 @@ unsafe extern "C" fn UndoLog_AddLabels(
     label_ids: *const LabelID,
     labels_count: usize,
 ) {
-    (*log).add_labels(
-        node.read(),
-        from_raw_parts(label_ids, labels_count).to_vec(),
-    );
+    ffi_guard!(log, (), {
+        (*log).add_labels(
+            node.read(),
+            from_raw_parts(label_ids, labels_count).to_vec(),
+        );
+    })
 }
 
 #[no_mangle]
@@ -96,10 +157,12 @@ unsafe extern "C" fn UndoLog_RemoveLabels(
     label_ids: *const LabelID,
     labels_count: usize,
 ) {
-    (*log).remove_labels(
-        node.read(),
-        from_raw_parts(label_ids, labels_count).to_vec(),
-    );
+    ffi_guard!(log, (), {
+        (*log).remove_labels(
+            node.read(),
+            from_raw_parts(label_ids, labels_count).to_vec(),
+        );
+    })
 }
 
 #[no_mangle]
@@ -108,7 +171,27 @@ unsafe extern "C" fn UndoLog_AddSchema(
     schema_id: i32,
     t: SchemaType,
 ) {
-    (*log).add_schema(schema_id, t);
+    ffi_guard!(log, (), {
+        (*log).add_schema(schema_id, t);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_RemoveSchema(
+    log: _UndoLog,
+    gc: *mut GraphContext,
+    schema_id: i32,
+    t: SchemaType,
+    name: *const c_char,
+) -> bool {
+    ffi_guard!(log, false, {
+        (*log).remove_schema(
+            &GraphContextAPI { context: gc },
+            schema_id,
+            t,
+            CStr::from_ptr(name),
+        )
+    })
 }
 
 #[no_mangle]
@@ -116,7 +199,25 @@ unsafe extern "C" fn UndoLog_AddAttribute(
     log: _UndoLog,
     attribute_id: AttributeID,
 ) {
-    (*log).add_attribute(attribute_id);
+    ffi_guard!(log, (), {
+        (*log).add_attribute(attribute_id);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_RemoveAttribute(
+    log: _UndoLog,
+    gc: *mut GraphContext,
+    attribute_id: AttributeID,
+    name: *const c_char,
+) -> bool {
+    ffi_guard!(log, false, {
+        (*log).remove_attribute(
+            &GraphContextAPI { context: gc },
+            attribute_id,
+            CStr::from_ptr(name),
+        )
+    })
 }
 
 #[no_mangle]
@@ -127,19 +228,115 @@ unsafe extern "C" fn UndoLog_CreateIndex(
     field: *const c_char,
     t: IndexFieldType,
 ) {
-    (*log).create_index(st, label, field, t);
+    ffi_guard!(log, (), {
+        (*log).create_index(st, label, field, t);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_DropIndex(
+    log: _UndoLog,
+    st: SchemaType,
+    label: *const c_char,
+    field: *const c_char,
+    t: IndexFieldType,
+) {
+    ffi_guard!(log, (), {
+        (*log).drop_index(st, CStr::from_ptr(label), CStr::from_ptr(field), t);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_CreateConstraint(
+    log: _UndoLog,
+    schema_id: i32,
+    t: SchemaType,
+    c: Constraint,
+) {
+    ffi_guard!(log, (), {
+        (*log).create_constraint(schema_id, t, c);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_DropConstraint(
+    log: _UndoLog,
+    schema_id: i32,
+    t: SchemaType,
+    c: Constraint,
+) {
+    ffi_guard!(log, (), {
+        (*log).drop_constraint(schema_id, t, c);
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_Savepoint(log: _UndoLog) -> SavepointId {
+    ffi_guard!(log, SavepointId::MAX, { (*log).savepoint() })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_RollbackTo(
+    log: _UndoLog,
+    gc: *mut GraphContext,
+    id: SavepointId,
+) -> bool {
+    ffi_guard!(log, false, {
+        (*log).rollback_to(id, &mut GraphContextAPI { context: gc }) == Ok(true)
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_Release(
+    log: _UndoLog,
+    id: SavepointId,
+) -> bool {
+    ffi_guard!(log, false, { (*log).release(id).is_ok() })
 }
 
 #[no_mangle]
 unsafe extern "C" fn UndoLog_Rollback(
     log: _UndoLog,
     gc: *mut GraphContext,
+) -> bool {
+    ffi_guard!(false, {
+        let mut log = Box::from_raw(log);
+        // a poisoned log can not be trusted to restore the graph
+        !log.is_poisoned() && log.rollback(&mut GraphContextAPI { context: gc })
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_EnableVerification(
+    log: _UndoLog,
+    gc: *mut GraphContext,
 ) {
-    (*log).rollback(&mut GraphContextAPI { context: gc });
-    drop(Box::from_raw(log));
+    ffi_guard!(log, (), {
+        (*log).enable_verification(&GraphContextAPI { context: gc });
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_Commit(
+    log: _UndoLog,
+    gc: *mut GraphContext,
+) {
+    ffi_guard!((), {
+        let log = Box::from_raw(log);
+        if !log.is_poisoned() {
+            log.commit(gc);
+        }
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn UndoLog_MemoryUsage(log: _UndoLog) -> usize {
+    ffi_guard!(log, 0, { (*log).memory_usage() })
 }
 
 #[no_mangle]
 unsafe extern "C" fn UndoLog_Free(log: _UndoLog) {
-    drop(Box::from_raw(log));
+    ffi_guard!((), {
+        drop(Box::from_raw(log));
+    })
 }
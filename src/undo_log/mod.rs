@@ -5,3 +5,4 @@
 
 mod ffi;
 mod undo_log;
+mod verify;
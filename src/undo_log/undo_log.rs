@@ -3,26 +3,124 @@
  * Licensed under the Server Side Public License v1 (SSPLv1).
  */
 
-use std::{ffi::c_char, ptr::null_mut};
+use std::{
+    cell::Cell,
+    ffi::{c_char, CStr, CString},
+    mem::size_of,
+    ptr::null_mut,
+};
 
-use crate::binding::graph::*;
+use crate::{
+    binding::graph::*,
+    cdc::feed::{self, ChangeEvent, ChangeType},
+    graph::entity::{EdgeMut, NodeMut},
+    guard::Poison,
+};
+
+use super::verify::{Fingerprint, Touched};
 
 enum UndoOp {
     CreateNodes(Vec<Node>),
     CreateEdges(Vec<Edge>),
-    DeleteNodes(Vec<(NodeID, AttributeSet, Vec<LabelID>)>),
+    DeleteNodes(DeletedNodes),
     DeleteEdges(Vec<(EntityID, NodeID, NodeID, RelationID, AttributeSet)>),
-    UpdateNodes(Vec<(Node, AttributeSet)>),
+    UpdateNodes(Vec<(NodeID, AttributeSet)>),
     UpdateEdges(Vec<(Edge, AttributeSet)>),
+    UpdateNodeAttrs(Vec<(NodeID, AttributeID, RawSIValue)>),
+    UpdateEdgeAttrs(Vec<(Edge, AttributeID, RawSIValue)>),
     AddLabels(Vec<(Node, Vec<LabelID>)>),
     RemoveLabels(Vec<(Node, Vec<LabelID>)>),
     AddSchema(SchemaID, SchemaType),
+    RemoveSchema(SchemaID, SchemaType, CString),
     AddAttribute(AttributeID),
+    RemoveAttribute(AttributeID, CString),
     CreateIndex(SchemaType, *const c_char, *const c_char, IndexFieldType),
+    DropIndex(SchemaType, CString, CString, IndexFieldType),
+    CreateConstraint(SchemaID, SchemaType, Constraint),
+    DropConstraint(SchemaID, SchemaType, Constraint),
+}
+
+// deleted nodes in an arena layout
+// bulk deletes record millions of nodes so avoid a heap allocation per node
+#[derive(Default)]
+struct DeletedNodes {
+    // runs of sequential ids (first id, run length)
+    ids: Vec<(NodeID, u32)>,
+    sets: Vec<AttributeSet>,
+    // labels of the i-th node are labels[offsets[i]..offsets[i + 1]]
+    labels: Vec<LabelID>,
+    offsets: Vec<u32>,
+}
+
+impl DeletedNodes {
+    fn push(
+        &mut self,
+        id: NodeID,
+        set: AttributeSet,
+        labels: &[LabelID],
+    ) {
+        match self.ids.last_mut() {
+            Some((first, len)) if *first + *len as NodeID == id && *len < u32::MAX => *len += 1,
+            _ => self.ids.push((id, 1)),
+        }
+        if self.offsets.is_empty() {
+            self.offsets.push(0);
+        }
+        self.sets.push(set);
+        self.labels.extend_from_slice(labels);
+        self.offsets.push(self.labels.len() as u32);
+    }
+
+    fn ids(&self) -> impl Iterator<Item = NodeID> + '_ {
+        self.ids
+            .iter()
+            .flat_map(|(first, len)| *first..*first + *len as NodeID)
+    }
+
+    // labels of the i-th deleted node
+    fn labels_mut(
+        &mut self,
+        i: usize,
+    ) -> &mut [LabelID] {
+        let start = self.offsets[i] as usize;
+        let end = self.offsets[i + 1] as usize;
+        &mut self.labels[start..end]
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.ids.capacity() * size_of::<(NodeID, u32)>()
+            + self.sets.capacity() * size_of::<AttributeSet>()
+            + self.labels.capacity() * size_of::<LabelID>()
+            + self.offsets.capacity() * size_of::<u32>()
+    }
 }
 
+// ids are never reused so a released savepoint can not be mistaken for a newer one
+pub type SavepointId = u64;
+
+/// The savepoint was released or rolled back past.
+#[derive(Debug, PartialEq)]
+pub struct InvalidSavepoint(pub SavepointId);
+
 pub struct UndoLog {
     ops: Vec<UndoOp>,
+    // live savepoints in the order they were taken
+    // with the number of ops recorded when each was taken
+    savepoints: Vec<(SavepointId, usize)>,
+    next_savepoint: SavepointId,
+    // graph state before the first op, set when verification is enabled
+    fingerprint: Option<Fingerprint>,
+    poisoned: Cell<bool>,
+}
+
+impl Poison for UndoLog {
+    fn poison(&self) {
+        self.poisoned.set(true);
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.poisoned.get()
+    }
 }
 
 impl Drop for UndoLog {
@@ -39,8 +137,8 @@ impl Drop for UndoLog {
                         unsafe { AttributeSet_Free(set) };
                     }
                 }
-                UndoOp::DeleteNodes(vec) => {
-                    for (_, set, _) in vec {
+                UndoOp::DeleteNodes(nodes) => {
+                    for set in nodes.sets.iter_mut() {
                         unsafe { AttributeSet_Free(set) };
                     }
                 }
@@ -49,6 +147,19 @@ impl Drop for UndoLog {
                         unsafe { AttributeSet_Free(set) };
                     }
                 }
+                UndoOp::UpdateNodeAttrs(vec) => {
+                    for (_, _, v) in vec {
+                        unsafe { SIValue_Free(*v) };
+                    }
+                }
+                UndoOp::UpdateEdgeAttrs(vec) => {
+                    for (_, _, v) in vec {
+                        unsafe { SIValue_Free(*v) };
+                    }
+                }
+                UndoOp::DropConstraint(_, _, c) => {
+                    unsafe { Constraint_Free(c) };
+                }
                 _ => {}
             }
         }
@@ -57,14 +168,52 @@ impl Drop for UndoLog {
 
 impl UndoLog {
     pub fn new() -> Self {
-        Self { ops: Vec::new() }
+        Self {
+            ops: Vec::new(),
+            savepoints: Vec::new(),
+            next_savepoint: 0,
+            fingerprint: None,
+            poisoned: Cell::new(false),
+        }
+    }
+
+    /// Verify on rollback that the graph is restored to its current state.
+    /// Must be called before the first op is recorded.
+    pub fn enable_verification(
+        &mut self,
+        gc: &GraphContextAPI,
+    ) {
+        debug_assert!(self.ops.is_empty());
+        self.fingerprint = Some(Fingerprint::capture(&gc.get_graph()));
+    }
+
+    fn touched(&self) -> Touched {
+        let mut touched = Touched::default();
+        for op in self.ops.iter() {
+            match op {
+                UndoOp::CreateNodes(nodes) => nodes.iter().for_each(|n| touched.create_node(n.id)),
+                UndoOp::CreateEdges(edges) => edges.iter().for_each(|e| touched.create_edge(e.id)),
+                UndoOp::DeleteNodes(nodes) => nodes.ids().for_each(|id| touched.delete_node(id)),
+                UndoOp::DeleteEdges(vec) => vec
+                    .iter()
+                    .for_each(|(id, _, _, _, _)| touched.delete_edge(*id)),
+                _ => {}
+            }
+        }
+        touched
+    }
+
+    // ops recorded before the last savepoint are never merged with new ops
+    // so rolling back to the savepoint keeps them intact
+    fn sealed(&self) -> usize {
+        self.savepoints.last().map_or(0, |(_, offset)| *offset)
     }
 
     pub fn create_node(
         &mut self,
         node: Node,
     ) {
-        if self.ops.is_empty() {
+        if self.ops.len() == self.sealed() {
             self.ops.push(UndoOp::CreateNodes(vec![node]));
             return;
         }
@@ -80,7 +229,7 @@ impl UndoLog {
         &mut self,
         edge: Edge,
     ) {
-        if self.ops.is_empty() {
+        if self.ops.len() == self.sealed() {
             self.ops.push(UndoOp::CreateEdges(vec![edge]));
             return;
         }
@@ -96,19 +245,15 @@ impl UndoLog {
         &mut self,
         node_id: NodeID,
         set: AttributeSet,
-        labels: Vec<LabelID>,
+        labels: &[LabelID],
     ) {
-        if self.ops.is_empty() {
-            self.ops
-                .push(UndoOp::DeleteNodes(vec![(node_id, set, labels)]));
-            return;
+        if self.ops.len() == self.sealed()
+            || !matches!(self.ops.last(), Some(UndoOp::DeleteNodes(_)))
+        {
+            self.ops.push(UndoOp::DeleteNodes(DeletedNodes::default()));
         }
-        let last_op = self.ops.last_mut().unwrap();
-        if let UndoOp::DeleteNodes(vec) = last_op {
-            vec.push((node_id, set, labels));
-        } else {
-            self.ops
-                .push(UndoOp::DeleteNodes(vec![(node_id, set, labels)]));
+        if let Some(UndoOp::DeleteNodes(nodes)) = self.ops.last_mut() {
+            nodes.push(node_id, set, labels);
         }
     }
 
@@ -120,7 +265,7 @@ impl UndoLog {
         relation_id: RelationID,
         set: AttributeSet,
     ) {
-        if self.ops.is_empty() {
+        if self.ops.len() == self.sealed() {
             self.ops.push(UndoOp::DeleteEdges(vec![(
                 edge_id,
                 src_id,
@@ -149,15 +294,15 @@ impl UndoLog {
         node: Node,
         old_set: AttributeSet,
     ) {
-        if self.ops.is_empty() {
-            self.ops.push(UndoOp::UpdateNodes(vec![(node, old_set)]));
+        if self.ops.len() == self.sealed() {
+            self.ops.push(UndoOp::UpdateNodes(vec![(node.id, old_set)]));
             return;
         }
         let last_op = self.ops.last_mut().unwrap();
         if let UndoOp::UpdateNodes(vec) = last_op {
-            vec.push((node, old_set));
+            vec.push((node.id, old_set));
         } else {
-            self.ops.push(UndoOp::UpdateNodes(vec![(node, old_set)]));
+            self.ops.push(UndoOp::UpdateNodes(vec![(node.id, old_set)]));
         }
     }
 
@@ -166,7 +311,7 @@ impl UndoLog {
         edge: Edge,
         old_set: AttributeSet,
     ) {
-        if self.ops.is_empty() {
+        if self.ops.len() == self.sealed() {
             self.ops.push(UndoOp::UpdateEdges(vec![(edge, old_set)]));
             return;
         }
@@ -178,12 +323,56 @@ impl UndoLog {
         }
     }
 
+    /// Record the old value of a single node attribute.
+    /// old_value is null when the attribute did not exist.
+    pub fn update_node_attr(
+        &mut self,
+        node_id: NodeID,
+        attr_id: AttributeID,
+        old_value: RawSIValue,
+    ) {
+        if self.ops.len() == self.sealed() {
+            self.ops
+                .push(UndoOp::UpdateNodeAttrs(vec![(node_id, attr_id, old_value)]));
+            return;
+        }
+        let last_op = self.ops.last_mut().unwrap();
+        if let UndoOp::UpdateNodeAttrs(vec) = last_op {
+            vec.push((node_id, attr_id, old_value));
+        } else {
+            self.ops
+                .push(UndoOp::UpdateNodeAttrs(vec![(node_id, attr_id, old_value)]));
+        }
+    }
+
+    /// Record the old value of a single edge attribute.
+    /// old_value is null when the attribute did not exist.
+    pub fn update_edge_attr(
+        &mut self,
+        edge: Edge,
+        attr_id: AttributeID,
+        old_value: RawSIValue,
+    ) {
+        if self.ops.len() == self.sealed() {
+            self.ops
+                .push(UndoOp::UpdateEdgeAttrs(vec![(edge, attr_id, old_value)]));
+            return;
+        }
+        let last_op = self.ops.last_mut().unwrap();
+        if let UndoOp::UpdateEdgeAttrs(vec) = last_op {
+            vec.push((edge, attr_id, old_value));
+        } else {
+            self.ops
+                .push(UndoOp::UpdateEdgeAttrs(vec![(edge, attr_id, old_value)]));
+        }
+    }
+
     pub fn add_labels(
         &mut self,
         node: Node,
         labels: Vec<LabelID>,
     ) {
-        if self.ops.is_empty() {
+        if self.ops.len() == self.sealed() {
             self.ops.push(UndoOp::AddLabels(vec![(node, labels)]));
             return;
         }
@@ -200,7 +389,7 @@ impl UndoLog {
         node: Node,
         labels: Vec<LabelID>,
     ) {
-        if self.ops.is_empty() {
+        if self.ops.len() == self.sealed() {
             self.ops.push(UndoOp::RemoveLabels(vec![(node, labels)]));
             return;
         }
@@ -220,6 +409,26 @@ impl UndoLog {
         self.ops.push(UndoOp::AddSchema(schema_id, schema_type));
     }
 
+    /// Record the removal of a schema, returns false and records nothing
+    /// if it is not the last schema as restoring it would assign a new id.
+    pub fn remove_schema(
+        &mut self,
+        gc: &GraphContextAPI,
+        schema_id: SchemaID,
+        schema_type: SchemaType,
+        name: &CStr,
+    ) -> bool {
+        if schema_id != gc.schema_count(schema_type) - 1 {
+            return false;
+        }
+        self.ops.push(UndoOp::RemoveSchema(
+            schema_id,
+            schema_type,
+            name.to_owned(),
+        ));
+        true
+    }
+
     pub fn add_attribute(
         &mut self,
         attribute_id: AttributeID,
@@ -227,6 +436,22 @@ impl UndoLog {
         self.ops.push(UndoOp::AddAttribute(attribute_id));
     }
 
+    /// Record the removal of an attribute, returns false and records nothing
+    /// if it is not the last attribute as restoring it would assign a new id.
+    pub fn remove_attribute(
+        &mut self,
+        gc: &GraphContextAPI,
+        attribute_id: AttributeID,
+        name: &CStr,
+    ) -> bool {
+        if attribute_id != gc.attribute_count() - 1 {
+            return false;
+        }
+        self.ops
+            .push(UndoOp::RemoveAttribute(attribute_id, name.to_owned()));
+        true
+    }
+
     pub fn create_index(
         &mut self,
         schema_type: SchemaType,
@@ -242,12 +467,257 @@ impl UndoLog {
         ));
     }
 
+    pub fn drop_index(
+        &mut self,
+        schema_type: SchemaType,
+        label: &CStr,
+        field: &CStr,
+        index_field_type: IndexFieldType,
+    ) {
+        self.ops.push(UndoOp::DropIndex(
+            schema_type,
+            label.to_owned(),
+            field.to_owned(),
+            index_field_type,
+        ));
+    }
+
+    pub fn create_constraint(
+        &mut self,
+        schema_id: SchemaID,
+        schema_type: SchemaType,
+        constraint: Constraint,
+    ) {
+        self.ops
+            .push(UndoOp::CreateConstraint(schema_id, schema_type, constraint));
+    }
+
+    pub fn drop_constraint(
+        &mut self,
+        schema_id: SchemaID,
+        schema_type: SchemaType,
+        constraint: Constraint,
+    ) {
+        self.ops
+            .push(UndoOp::DropConstraint(schema_id, schema_type, constraint));
+    }
+
+    /// Returns the changes recorded in this [`UndoLog`] in the order they were made.
+    pub fn changes(&self) -> Vec<ChangeEvent> {
+        let mut events = Vec::new();
+        for op in self.ops.iter() {
+            match op {
+                UndoOp::CreateNodes(nodes) => events.extend(
+                    nodes
+                        .iter()
+                        .map(|n| ChangeEvent::node(ChangeType::NodeCreated, n.id)),
+                ),
+                UndoOp::CreateEdges(edges) => events.extend(
+                    edges
+                        .iter()
+                        .map(|e| ChangeEvent::edge(ChangeType::EdgeCreated, e)),
+                ),
+                UndoOp::DeleteNodes(nodes) => events.extend(
+                    nodes
+                        .ids()
+                        .map(|id| ChangeEvent::node(ChangeType::NodeDeleted, id)),
+                ),
+                UndoOp::DeleteEdges(vec) => {
+                    events.extend(vec.iter().map(|(id, src_id, dest_id, relation_id, _)| {
+                        ChangeEvent {
+                            change_type: ChangeType::EdgeDeleted,
+                            id: *id,
+                            src_id: *src_id,
+                            dest_id: *dest_id,
+                            relation_id: *relation_id,
+                            labels: vec![],
+                        }
+                    }))
+                }
+                UndoOp::UpdateNodes(vec) => events.extend(
+                    vec.iter()
+                        .map(|(id, _)| ChangeEvent::node(ChangeType::NodeUpdated, *id)),
+                ),
+                UndoOp::UpdateEdges(vec) => events.extend(
+                    vec.iter()
+                        .map(|(e, _)| ChangeEvent::edge(ChangeType::EdgeUpdated, e)),
+                ),
+                UndoOp::UpdateNodeAttrs(vec) => events.extend(
+                    vec.iter()
+                        .map(|(id, _, _)| ChangeEvent::node(ChangeType::NodeUpdated, *id)),
+                ),
+                UndoOp::UpdateEdgeAttrs(vec) => events.extend(
+                    vec.iter()
+                        .map(|(e, _, _)| ChangeEvent::edge(ChangeType::EdgeUpdated, e)),
+                ),
+                UndoOp::AddLabels(vec) => {
+                    events.extend(vec.iter().map(|(n, labels)| {
+                        ChangeEvent::labels(ChangeType::LabelsAdded, n.id, labels)
+                    }))
+                }
+                UndoOp::RemoveLabels(vec) => events.extend(vec.iter().map(|(n, labels)| {
+                    ChangeEvent::labels(ChangeType::LabelsRemoved, n.id, labels)
+                })),
+                _ => {}
+            }
+        }
+        events
+    }
+
+    /// The transaction committed, publish its changes to the CDC subscribers.
+    pub fn commit(
+        self,
+        gc: *mut GraphContext,
+    ) {
+        if feed::has_subscribers() {
+            feed::publish(gc, &self.changes());
+        }
+    }
+
+    /// Returns the number of bytes allocated by this [`UndoLog`].
+    /// Attribute sets are owned by the engine and not counted.
+    pub fn memory_usage(&self) -> usize {
+        let mut size = self.ops.capacity() * size_of::<UndoOp>()
+            + self.savepoints.capacity() * size_of::<(SavepointId, usize)>();
+        for op in self.ops.iter() {
+            size += match op {
+                UndoOp::CreateNodes(nodes) => nodes.capacity() * size_of::<Node>(),
+                UndoOp::CreateEdges(edges) => edges.capacity() * size_of::<Edge>(),
+                UndoOp::DeleteNodes(nodes) => nodes.memory_usage(),
+                UndoOp::DeleteEdges(vec) => {
+                    vec.capacity()
+                        * size_of::<(EntityID, NodeID, NodeID, RelationID, AttributeSet)>()
+                }
+                UndoOp::UpdateNodes(vec) => vec.capacity() * size_of::<(NodeID, AttributeSet)>(),
+                UndoOp::UpdateEdges(vec) => vec.capacity() * size_of::<(Edge, AttributeSet)>(),
+                UndoOp::UpdateNodeAttrs(vec) => {
+                    vec.capacity() * size_of::<(NodeID, AttributeID, RawSIValue)>()
+                }
+                UndoOp::UpdateEdgeAttrs(vec) => {
+                    vec.capacity() * size_of::<(Edge, AttributeID, RawSIValue)>()
+                }
+                UndoOp::AddLabels(vec) | UndoOp::RemoveLabels(vec) => {
+                    vec.iter()
+                        .map(|(_, labels)| labels.capacity() * size_of::<LabelID>())
+                        .sum::<usize>()
+                        + vec.capacity() * size_of::<(Node, Vec<LabelID>)>()
+                }
+                _ => 0,
+            };
+        }
+        size
+    }
+
+    pub fn savepoint(&mut self) -> SavepointId {
+        let id = self.next_savepoint;
+        self.next_savepoint += 1;
+        self.savepoints.push((id, self.ops.len()));
+        id
+    }
+
+    fn position(
+        &self,
+        id: SavepointId,
+    ) -> Result<usize, InvalidSavepoint> {
+        // ids are increasing so the savepoints are sorted
+        self.savepoints
+            .binary_search_by_key(&id, |(id, _)| *id)
+            .map_err(|_| InvalidSavepoint(id))
+    }
+
+    /// Release the savepoint and the savepoints taken after it, keeping their ops.
+    pub fn release(
+        &mut self,
+        id: SavepointId,
+    ) -> Result<(), InvalidSavepoint> {
+        let pos = self.position(id)?;
+        self.savepoints.truncate(pos);
+        Ok(())
+    }
+
+    // drop the savepoints taken after id and returns the ops recorded since id
+    fn unwind_to(
+        &mut self,
+        id: SavepointId,
+    ) -> Result<Vec<UndoOp>, InvalidSavepoint> {
+        let pos = self.position(id)?;
+        // the savepoint stays valid so it can be rolled back to again
+        self.savepoints.truncate(pos + 1);
+        Ok(self.ops.split_off(self.savepoints[pos].1))
+    }
+
+    /// Undo the ops recorded since the savepoint was taken,
+    /// returns false if a deleted entity could not be restored.
+    pub unsafe fn rollback_to(
+        &mut self,
+        id: SavepointId,
+        gc: &mut GraphContextAPI,
+    ) -> Result<bool, InvalidSavepoint> {
+        let ops = self.unwind_to(id)?;
+        Ok(Self::undo(ops, gc))
+    }
+
+    /// Undo all ops, returns false if a deleted entity could not be restored
+    /// or if verification is enabled and the graph does not match its state
+    /// before the first op.
     pub unsafe fn rollback(
         &mut self,
         gc: &mut GraphContextAPI,
+    ) -> bool {
+        log::debug!("rolling back {} ops", self.ops.len());
+        self.savepoints.clear();
+        let touched = self.fingerprint.as_ref().map(|_| self.touched());
+        let restored = Self::undo(std::mem::take(&mut self.ops), gc);
+
+        let (Some(fingerprint), Some(touched)) = (self.fingerprint.as_ref(), touched) else {
+            return restored;
+        };
+        let mismatches = fingerprint.verify(&gc.get_graph(), &touched);
+        for m in mismatches.iter() {
+            log::error!("rollback verification failed: {m}");
+        }
+        restored && mismatches.is_empty()
+    }
+
+    // restore the old values in reverse so the first value recorded for an attribute wins
+    // reindex updates the indices of each restored attribute
+    unsafe fn revert_node_attrs(
+        g: &mut GraphAPI,
+        diffs: Vec<(NodeID, AttributeID, RawSIValue)>,
+        mut reindex: impl FnMut(*mut Node, AttributeID),
+    ) {
+        for (node_id, attr_id, old_value) in diffs.into_iter().rev() {
+            if let Some(mut node) = NodeMut::new(g, node_id) {
+                node.set_property(attr_id, old_value);
+                reindex(node.as_raw(), attr_id);
+            } else {
+                debug_assert!(false, "updated node {node_id} is missing");
+            }
+            SIValue_Free(old_value);
+        }
+    }
+
+    unsafe fn revert_edge_attrs(
+        g: &mut GraphAPI,
+        diffs: Vec<(Edge, AttributeID, RawSIValue)>,
+        mut reindex: impl FnMut(*mut Edge, AttributeID),
     ) {
+        for (edge, attr_id, old_value) in diffs.into_iter().rev() {
+            let mut edge = EdgeMut::from_raw(g, edge);
+            edge.set_property(attr_id, old_value);
+            reindex(edge.as_raw(), attr_id);
+            SIValue_Free(old_value);
+        }
+    }
+
+    // returns false if a deleted entity could not be restored as its id is in use
+    unsafe fn undo(
+        ops: Vec<UndoOp>,
+        gc: &mut GraphContextAPI,
+    ) -> bool {
+        let mut restored = true;
         let mut g = gc.get_graph();
-        for op in self.ops.drain(..).rev() {
+        for op in ops.into_iter().rev() {
             match op {
                 UndoOp::CreateNodes(mut nodes) => {
                     for node in nodes.iter_mut().rev() {
@@ -261,16 +731,31 @@ impl UndoLog {
                     }
                     g.delete_edges(edges.as_mut_ptr(), edges.len() as u64);
                 }
-                UndoOp::DeleteNodes(mut vec) => {
-                    for (node_id, set, labels) in vec.iter_mut().rev() {
-                        let mut node = Node {
-                            attributes: null_mut(),
-                            id: -1,
-                        };
-                        g.create_node(&mut node, labels.as_mut_ptr(), labels.len() as u32);
-                        debug_assert!(*node_id >= node.id);
-                        node.attributes.write(*set);
-                        gc.add_node_to_indices(&mut node);
+                UndoOp::DeleteNodes(mut nodes) => {
+                    let mut i = nodes.sets.len();
+                    for &(first, len) in std::mem::take(&mut nodes.ids).iter().rev() {
+                        for node_id in (first..first + len as NodeID).rev() {
+                            i -= 1;
+                            let labels = nodes.labels_mut(i);
+                            let mut node = Node {
+                                attributes: null_mut(),
+                                id: -1,
+                            };
+                            if !g.create_node_with_id(
+                                node_id,
+                                &mut node,
+                                labels.as_mut_ptr(),
+                                labels.len() as u32,
+                            ) {
+                                log::error!("rollback failed: deleted node {node_id} id is in use");
+                                AttributeSet_Free(&mut nodes.sets[i]);
+                                restored = false;
+                                continue;
+                            }
+                            debug_assert_eq!(node.id, node_id);
+                            node.attributes.write(nodes.sets[i]);
+                            gc.add_node_to_indices(&mut node);
+                        }
                     }
                 }
                 UndoOp::DeleteEdges(mut vec) => {
@@ -283,16 +768,31 @@ impl UndoLog {
                             src_id: *src_id,
                             dest_id: *dest_id,
                         };
-                        g.create_edge(edge.src_id, edge.dest_id, edge.relation_id, &mut edge);
-                        debug_assert!(*edge_id >= edge.id);
+                        if !g.create_edge_with_id(
+                            *edge_id,
+                            edge.src_id,
+                            edge.dest_id,
+                            edge.relation_id,
+                            &mut edge,
+                        ) {
+                            log::error!("rollback failed: deleted edge {edge_id} id is in use");
+                            AttributeSet_Free(set);
+                            restored = false;
+                            continue;
+                        }
+                        debug_assert_eq!(edge.id, *edge_id);
                         edge.attributes.write(*set);
                         gc.add_edge_to_indices(&mut edge);
                     }
                 }
                 UndoOp::UpdateNodes(mut vec) => {
-                    for (node, old_set) in vec.iter_mut().rev() {
+                    for (node_id, old_set) in vec.iter_mut().rev() {
+                        let Some(mut node) = g.get_node(*node_id) else {
+                            debug_assert!(false, "updated node {node_id} is missing");
+                            continue;
+                        };
                         node.set_attributes(old_set);
-                        gc.add_node_to_indices(node);
+                        gc.add_node_to_indices(&mut node);
                     }
                 }
                 UndoOp::UpdateEdges(mut vec) => {
@@ -301,6 +801,16 @@ impl UndoLog {
                         gc.add_edge_to_indices(edge);
                     }
                 }
+                UndoOp::UpdateNodeAttrs(vec) => {
+                    Self::revert_node_attrs(&mut g, vec, |n, attr_id| {
+                        gc.update_node_attr_in_indices(n, attr_id)
+                    });
+                }
+                UndoOp::UpdateEdgeAttrs(vec) => {
+                    Self::revert_edge_attrs(&mut g, vec, |e, attr_id| {
+                        gc.update_edge_attr_in_indices(e, attr_id)
+                    });
+                }
                 UndoOp::AddLabels(mut vec) => {
                     for (node, labels) in vec.iter_mut().rev() {
                         gc.delete_node_from_indices(node, labels.as_mut_ptr(), labels.len() as u32);
@@ -321,13 +831,430 @@ impl UndoLog {
                         g.remove_relation(schema_id);
                     }
                 }
+                UndoOp::RemoveSchema(schema_id, schema_type, name) => {
+                    // adding the schema adds its matrix too
+                    let id = gc.add_schema(name.as_ptr(), schema_type);
+                    debug_assert_eq!(id, schema_id);
+                }
                 UndoOp::AddAttribute(attribute_id) => {
                     gc.remove_attribute(attribute_id);
                 }
+                UndoOp::RemoveAttribute(attribute_id, name) => {
+                    let id = gc.find_or_add_attribute(name.as_ptr());
+                    debug_assert_eq!(id, attribute_id);
+                }
                 UndoOp::CreateIndex(schema_type, label, field, index_field_type) => {
                     gc.delete_index(schema_type, label, field, index_field_type);
                 }
+                UndoOp::DropIndex(schema_type, label, field, index_field_type) => {
+                    gc.add_index(
+                        schema_type,
+                        label.as_ptr(),
+                        field.as_ptr(),
+                        index_field_type,
+                    );
+                }
+                UndoOp::CreateConstraint(schema_id, schema_type, mut c) => {
+                    gc.remove_constraint(schema_id, schema_type, c);
+                    Constraint_Free(&mut c);
+                }
+                UndoOp::DropConstraint(schema_id, schema_type, c) => {
+                    gc.add_constraint(schema_id, schema_type, c);
+                }
             }
         }
+        restored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{mem::size_of, ptr::null_mut};
+
+    use crate::{
+        binding::graph::{
+            AttributeID, AttributeSet, AttributeSet_Free, AttributeSet_Get, AttributeSet_Update,
+            Edge, GraphAPI, LabelID, Node, NodeID, RawSIValue, SIValueData, SI_CloneValue, T_INT64,
+        },
+        cdc::feed::ChangeType,
+    };
+
+    #[cfg(feature = "rust_graph")]
+    use crate::graph::{
+        matrix::GraphBLAS::{GrB_Mode, GrB_init},
+        Graph,
+    };
+
+    #[cfg(feature = "rust_graph")]
+    use super::Fingerprint;
+    use super::{DeletedNodes, InvalidSavepoint, UndoLog, UndoOp};
+
+    fn create_node(
+        log: &mut UndoLog,
+        id: NodeID,
+    ) {
+        log.create_node(Node {
+            attributes: null_mut(),
+            id,
+        });
+    }
+
+    fn created(log: &UndoLog) -> Vec<NodeID> {
+        log.changes()
+            .iter()
+            .map(|e| {
+                assert_eq!(e.change_type, ChangeType::NodeCreated);
+                e.id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nested_rollback() {
+        let mut log = UndoLog::new();
+        create_node(&mut log, 0);
+        let outer = log.savepoint();
+        create_node(&mut log, 1);
+        let inner = log.savepoint();
+        create_node(&mut log, 2);
+        create_node(&mut log, 3);
+
+        let ops = log.unwind_to(inner).unwrap();
+        assert_eq!(ops.len(), 1);
+        assert_eq!(created(&log), vec![0, 1]);
+
+        // the savepoint can be rolled back to again
+        create_node(&mut log, 4);
+        log.unwind_to(inner).unwrap();
+        assert_eq!(created(&log), vec![0, 1]);
+
+        log.unwind_to(outer).unwrap();
+        assert_eq!(created(&log), vec![0]);
+
+        // rolling back to outer drops inner
+        assert_eq!(log.unwind_to(inner).err(), Some(InvalidSavepoint(inner)));
+        assert_eq!(log.release(inner), Err(InvalidSavepoint(inner)));
+        assert_eq!(log.release(outer), Ok(()));
+    }
+
+    #[test]
+    fn test_release() {
+        let mut log = UndoLog::new();
+        let outer = log.savepoint();
+        create_node(&mut log, 0);
+        let inner = log.savepoint();
+        create_node(&mut log, 1);
+
+        // releasing outer releases inner and keeps their ops
+        assert_eq!(log.release(outer), Ok(()));
+        assert_eq!(log.release(inner), Err(InvalidSavepoint(inner)));
+        assert_eq!(created(&log), vec![0, 1]);
+
+        // a new savepoint never reuses a released id
+        let next = log.savepoint();
+        assert_ne!(next, outer);
+        assert_ne!(next, inner);
+        assert_eq!(log.unwind_to(outer).err(), Some(InvalidSavepoint(outer)));
+
+        create_node(&mut log, 2);
+        log.unwind_to(next).unwrap();
+        assert_eq!(created(&log), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_deleted_nodes() {
+        let deleted: [(NodeID, &[LabelID]); 6] = [
+            (5, &[1]),
+            (6, &[]),
+            (7, &[2, 3]),
+            (10, &[4]),
+            (11, &[]),
+            (3, &[]),
+        ];
+        let mut nodes = DeletedNodes::default();
+        for (i, (id, labels)) in deleted.iter().enumerate() {
+            nodes.push(*id, i as AttributeSet, labels);
+        }
+
+        // sequential ids share a run
+        assert_eq!(nodes.ids, vec![(5, 3), (10, 2), (3, 1)]);
+        assert_eq!(nodes.labels, vec![1, 2, 3, 4]);
+        assert_eq!(
+            nodes.ids().collect::<Vec<_>>(),
+            deleted.iter().map(|(id, _)| *id).collect::<Vec<_>>()
+        );
+        for (i, (_, labels)) in deleted.iter().enumerate() {
+            assert_eq!(nodes.labels_mut(i), *labels);
+            assert_eq!(nodes.sets[i], i as AttributeSet);
+        }
+    }
+
+    #[test]
+    fn test_deleted_nodes_memory_usage() {
+        let count = 1000;
+        let mut nodes = DeletedNodes::default();
+        for id in 0..count {
+            nodes.push(id, null_mut(), &[]);
+        }
+        assert_eq!(nodes.ids.len(), 1);
+
+        let usage = nodes.memory_usage();
+        assert!(usage >= count as usize * (size_of::<AttributeSet>() + size_of::<u32>()));
+        // a node id, attribute set and label vector per node
+        let naive = count as usize
+            * (size_of::<NodeID>() + size_of::<AttributeSet>() + size_of::<Vec<LabelID>>());
+        assert!(usage < naive);
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let mut log = UndoLog::new();
+        assert_eq!(log.memory_usage(), 0);
+
+        for id in 0..10 {
+            create_node(&mut log, id);
+        }
+        let UndoOp::CreateNodes(nodes) = &log.ops[0] else {
+            panic!("expected created nodes");
+        };
+        assert_eq!(
+            log.memory_usage(),
+            log.ops.capacity() * size_of::<UndoOp>() + nodes.capacity() * size_of::<Node>()
+        );
+
+        let before = log.memory_usage();
+        log.add_labels(
+            Node {
+                attributes: null_mut(),
+                id: 0,
+            },
+            vec![1, 2, 3],
+        );
+        assert!(log.memory_usage() >= before + 3 * size_of::<LabelID>());
+    }
+
+    fn int(v: i64) -> RawSIValue {
+        RawSIValue {
+            data: SIValueData { longval: v },
+            type_: T_INT64,
+            allocation: 0,
+        }
+    }
+
+    fn get_int(
+        set: AttributeSet,
+        id: AttributeID,
+    ) -> Option<i64> {
+        unsafe { AttributeSet_Get(set, id).as_ref() }.map(|v| unsafe { v.data.longval })
+    }
+
+    // update the attributes recording their old values as the engine does
+    fn update(
+        set: *mut AttributeSet,
+        updates: &[(AttributeID, i64)],
+        mut record: impl FnMut(AttributeID, RawSIValue),
+    ) {
+        for (attr_id, v) in updates {
+            let old = unsafe { AttributeSet_Get(*set, *attr_id).as_ref() }
+                .map_or(RawSIValue::null(), |v| unsafe { SI_CloneValue(*v) });
+            record(*attr_id, old);
+            unsafe { AttributeSet_Update(set, *attr_id, int(*v)) };
+        }
+    }
+
+    #[test]
+    fn test_update_edge_attrs() {
+        let mut set: AttributeSet = null_mut();
+        unsafe { AttributeSet_Update(&mut set, 1, int(1)) };
+        let edge = Edge {
+            attributes: &mut set,
+            id: 7,
+            relationship: null_mut(),
+            relation_id: 0,
+            src_id: 1,
+            dest_id: 2,
+        };
+
+        let mut log = UndoLog::new();
+        update(&mut set, &[(1, 2), (1, 3), (2, 4)], |attr_id, old| {
+            log.update_edge_attr(edge, attr_id, old)
+        });
+        assert_eq!(get_int(set, 1), Some(3));
+        assert_eq!(get_int(set, 2), Some(4));
+
+        // consecutive diffs share an op
+        assert_eq!(log.ops.len(), 1);
+        let changes = log.changes();
+        assert_eq!(changes.len(), 3);
+        assert!(changes
+            .iter()
+            .all(|e| e.change_type == ChangeType::EdgeUpdated && e.id == 7));
+
+        let Some(UndoOp::UpdateEdgeAttrs(diffs)) = log.ops.pop() else {
+            panic!("expected edge attribute diffs");
+        };
+        let mut reindexed = vec![];
+        let mut g = GraphAPI { graph: null_mut() };
+        unsafe {
+            UndoLog::revert_edge_attrs(&mut g, diffs, |e, attr_id| {
+                assert_eq!((*e).id, 7);
+                reindexed.push(attr_id);
+            })
+        };
+        assert_eq!(reindexed, vec![2, 1, 1]);
+        assert_eq!(get_int(set, 1), Some(1));
+        assert_eq!(get_int(set, 2), None);
+
+        unsafe { AttributeSet_Free(&mut set) };
+    }
+
+    #[cfg(feature = "rust_graph")]
+    fn test_graph() -> Graph {
+        unsafe { GrB_init(GrB_Mode::GrB_NONBLOCKING) };
+        Graph::new(16, 16)
+    }
+
+    #[cfg(feature = "rust_graph")]
+    fn graph_api(graph: &mut Graph) -> GraphAPI {
+        GraphAPI {
+            graph: graph as *mut Graph as *mut _,
+        }
+    }
+
+    #[cfg(feature = "rust_graph")]
+    fn new_node(graph: &mut Graph) -> Node {
+        let mut node = Node {
+            attributes: null_mut(),
+            id: -1,
+        };
+        graph.create_node(&mut node, &[]);
+        node
+    }
+
+    #[cfg(feature = "rust_graph")]
+    #[test]
+    fn test_update_node_attrs() {
+        let mut graph = test_graph();
+        let node = new_node(&mut graph);
+        unsafe { AttributeSet_Update(node.attributes, 1, int(1)) };
+
+        let mut log = UndoLog::new();
+        update(
+            node.attributes,
+            &[(2, 2), (1, 3), (2, 4)],
+            |attr_id, old| log.update_node_attr(node.id, attr_id, old),
+        );
+        assert_eq!(get_int(unsafe { *node.attributes }, 2), Some(4));
+
+        assert_eq!(log.ops.len(), 1);
+        assert!(log
+            .changes()
+            .iter()
+            .all(|e| e.change_type == ChangeType::NodeUpdated && e.id == node.id));
+
+        let Some(UndoOp::UpdateNodeAttrs(diffs)) = log.ops.pop() else {
+            panic!("expected node attribute diffs");
+        };
+        let mut reindexed = vec![];
+        unsafe {
+            UndoLog::revert_node_attrs(&mut graph_api(&mut graph), diffs, |_, attr_id| {
+                reindexed.push(attr_id)
+            })
+        };
+        assert_eq!(reindexed, vec![2, 1, 2]);
+        assert_eq!(get_int(unsafe { *node.attributes }, 1), Some(1));
+        assert_eq!(get_int(unsafe { *node.attributes }, 2), None);
+    }
+
+    #[test]
+    fn test_touched() {
+        let mut log = UndoLog::new();
+        for id in 0..1000 {
+            create_node(&mut log, id);
+        }
+        for id in 500..1500 {
+            log.delete_node(id, null_mut(), &[]);
+        }
+
+        // nodes created and deleted by the transaction are neither
+        let touched = log.touched();
+        assert_eq!(touched.created_nodes.len(), 1000);
+        assert_eq!(touched.deleted_nodes, (1000..1500).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_touched_id_reuse() {
+        let mut log = UndoLog::new();
+        // existing node 7 is deleted and a create reuses its id
+        log.delete_node(7, null_mut(), &[]);
+        create_node(&mut log, 7);
+        create_node(&mut log, 8);
+        log.delete_node(7, null_mut(), &[]);
+
+        let touched = log.touched();
+        assert_eq!(touched.created_nodes.len(), 1);
+        assert!(touched.created_nodes.contains(&8));
+        assert_eq!(touched.deleted_nodes, vec![7]);
+    }
+
+    #[cfg(feature = "rust_graph")]
+    #[test]
+    fn test_verification() {
+        let mut graph = test_graph();
+        let label = graph.add_label();
+        let n0 = new_node(&mut graph);
+        let n1 = new_node(&mut graph);
+        graph.label_node(n0.id, &[label]);
+
+        let mut log = UndoLog::new();
+        log.fingerprint = Some(Fingerprint::capture(&graph_api(&mut graph)));
+
+        // create n2, delete n1 and remove the label of n0
+        let n2 = new_node(&mut graph);
+        log.create_node(n2);
+        graph.delete_nodes(&[n1]);
+        log.delete_node(n1.id, null_mut(), &[]);
+        graph.remove_node_labels(n0.id, &[label]);
+        log.remove_labels(n0, vec![label]);
+
+        let fingerprint = log.fingerprint.as_ref().unwrap();
+        let mismatches = fingerprint.verify(&graph_api(&mut graph), &log.touched());
+        assert_eq!(mismatches.len(), 3, "{mismatches:?}");
+
+        // undo the changes by hand
+        graph.delete_nodes(&[n2]);
+        let mut restored = n1;
+        assert!(graph.create_node_with_id(n1.id, &mut restored, &[]));
+        graph.label_node(n0.id, &[label]);
+        assert!(fingerprint
+            .verify(&graph_api(&mut graph), &log.touched())
+            .is_empty());
+    }
+
+    #[cfg(feature = "rust_graph")]
+    #[test]
+    fn test_verification_id_reuse() {
+        let mut graph = test_graph();
+        new_node(&mut graph);
+        let n1 = new_node(&mut graph);
+
+        let mut log = UndoLog::new();
+        log.fingerprint = Some(Fingerprint::capture(&graph_api(&mut graph)));
+
+        // delete n1 and create a node that reuses its id
+        graph.delete_nodes(&[n1]);
+        log.delete_node(n1.id, null_mut(), &[]);
+        let n2 = new_node(&mut graph);
+        assert_eq!(n2.id, n1.id);
+        log.create_node(n2);
+
+        // a correct rollback deletes n2 and restores n1
+        graph.delete_nodes(&[n2]);
+        let mut restored = n1;
+        assert!(graph.create_node_with_id(n1.id, &mut restored, &[]));
+        let fingerprint = log.fingerprint.as_ref().unwrap();
+        assert!(fingerprint
+            .verify(&graph_api(&mut graph), &log.touched())
+            .is_empty());
     }
 }
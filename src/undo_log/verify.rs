@@ -0,0 +1,154 @@
+/*
+ * Copyright FalkorDB Ltd. 2023 - present
+ * Licensed under the Server Side Public License v1 (SSPLv1).
+ */
+
+use std::collections::HashSet;
+
+use crate::binding::graph::*;
+
+/// Entities touched by a transaction.
+/// After rollback created entities must be gone and deleted entities restored.
+#[derive(Default)]
+pub struct Touched {
+    pub created_nodes: HashSet<NodeID>,
+    pub deleted_nodes: Vec<NodeID>,
+    pub created_edges: HashSet<EntityID>,
+    pub deleted_edges: Vec<EntityID>,
+    // deleted ids for lookup, the vectors keep the deletion order
+    deleted_node_ids: HashSet<NodeID>,
+    deleted_edge_ids: HashSet<EntityID>,
+}
+
+impl Touched {
+    // Ops must be recorded in transaction order.
+    // An entity created then deleted by the transaction is neither,
+    // an existing entity deleted then its id reused by a create stays deleted
+    // as the rollback restores it.
+
+    pub fn create_node(
+        &mut self,
+        id: NodeID,
+    ) {
+        if !self.deleted_node_ids.contains(&id) {
+            self.created_nodes.insert(id);
+        }
+    }
+
+    pub fn delete_node(
+        &mut self,
+        id: NodeID,
+    ) {
+        if !self.created_nodes.remove(&id) && self.deleted_node_ids.insert(id) {
+            self.deleted_nodes.push(id);
+        }
+    }
+
+    pub fn create_edge(
+        &mut self,
+        id: EntityID,
+    ) {
+        if !self.deleted_edge_ids.contains(&id) {
+            self.created_edges.insert(id);
+        }
+    }
+
+    pub fn delete_edge(
+        &mut self,
+        id: EntityID,
+    ) {
+        if !self.created_edges.remove(&id) && self.deleted_edge_ids.insert(id) {
+            self.deleted_edges.push(id);
+        }
+    }
+}
+
+/// Snapshot of the graph taken before the first op of a transaction.
+pub struct Fingerprint {
+    node_count: u64,
+    edge_count: u64,
+    label_nvals: Vec<u64>,
+}
+
+impl Fingerprint {
+    pub fn capture(g: &GraphAPI) -> Self {
+        Self {
+            node_count: g.node_count(),
+            edge_count: g.edge_count(),
+            label_nvals: (0..g.label_count())
+                .map(|l| g.label_matrix(l).nvals())
+                .collect(),
+        }
+    }
+
+    /// Compare the graph after rollback to the fingerprint.
+    /// Returns a description of every mismatch found.
+    pub fn verify(
+        &self,
+        g: &GraphAPI,
+        touched: &Touched,
+    ) -> Vec<String> {
+        let mut mismatches = Vec::new();
+
+        let node_count = g.node_count();
+        if node_count != self.node_count {
+            mismatches.push(format!(
+                "node count {node_count} expected {}",
+                self.node_count
+            ));
+        }
+        let edge_count = g.edge_count();
+        if edge_count != self.edge_count {
+            mismatches.push(format!(
+                "edge count {edge_count} expected {}",
+                self.edge_count
+            ));
+        }
+
+        // labels created by the transaction are removed by the rollback
+        let label_count = g.label_count() as usize;
+        if label_count != self.label_nvals.len() {
+            mismatches.push(format!(
+                "label count {label_count} expected {}",
+                self.label_nvals.len()
+            ));
+        }
+        for (l, expected) in self.label_nvals.iter().enumerate().take(label_count) {
+            let nvals = g.label_matrix(l as LabelID).nvals();
+            if nvals != *expected {
+                mismatches.push(format!("label {l} nvals {nvals} expected {expected}"));
+            }
+        }
+
+        for id in touched
+            .created_nodes
+            .iter()
+            .filter(|id| g.contains_node(**id))
+        {
+            mismatches.push(format!("created node {id} still exists"));
+        }
+        for id in touched
+            .deleted_nodes
+            .iter()
+            .filter(|id| !g.contains_node(**id))
+        {
+            mismatches.push(format!("deleted node {id} was not restored"));
+        }
+        for id in touched
+            .created_edges
+            .iter()
+            .filter(|id| g.contains_edge(**id))
+        {
+            mismatches.push(format!("created edge {id} still exists"));
+        }
+        for id in touched
+            .deleted_edges
+            .iter()
+            .filter(|id| !g.contains_edge(**id))
+        {
+            mismatches.push(format!("deleted edge {id} was not restored"));
+        }
+
+        mismatches
+    }
+}